@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uttt::book::Book;
+
+// raw bytes: the header is part of what gets fuzzed
+fuzz_target!(|bytes: &[u8]| {
+    let _ = Book::read_from(bytes);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uttt::features::parse_game_line;
+use uttt::Bitboard;
+
+fuzz_target!(|s: &str| {
+    let _ = parse_game_line(Bitboard::new(), s);
+});
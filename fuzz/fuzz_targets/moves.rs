@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uttt::notation::parse_moves_from;
+use uttt::Bitboard;
+
+fuzz_target!(|s: &str| {
+    let _ = parse_moves_from(Bitboard::new(), s.split_whitespace());
+});
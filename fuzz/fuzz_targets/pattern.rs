@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uttt::pattern::Pattern;
+
+fuzz_target!(|s: &str| {
+    let _ = Pattern::parse(s);
+});
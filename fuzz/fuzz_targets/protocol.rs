@@ -0,0 +1,23 @@
+#![no_main]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use libfuzzer_sys::fuzz_target;
+use uttt::protocol::Engine;
+
+// one command per line; a search is stopped right after its `go`, since the
+// next command would wait for it, and the Hash option is skipped so that an
+// input cannot ask for gigabytes
+fuzz_target!(|s: &str| {
+    let mut engine = Engine::with_output(Arc::new(Mutex::new(io::sink())));
+    for line in s.lines() {
+        if line.split_whitespace().any(|token| token == "Hash") {
+            continue;
+        }
+        if !engine.handle(line) {
+            break;
+        }
+        engine.stop();
+    }
+});
@@ -0,0 +1,50 @@
+//! Heap allocation counting for `bench --assert-no-alloc`.
+//!
+//! The library only provides [`CountingAllocator`]; the binary installs it as
+//! the global allocator, so that linking the library never replaces the
+//! allocator of an embedding application.
+
+#[cfg(feature = "alloc-tracker")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator wrapper counting every allocation and reallocation; counts
+/// are only seen by [`count`] where it is the `#[global_allocator]`.
+#[cfg(feature = "alloc-tracker")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "alloc-tracker")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+pub fn enabled() -> bool {
+    cfg!(feature = "alloc-tracker")
+}
+
+/// Number of heap allocations made while running `func`, if tracking is enabled.
+pub fn count<F: FnOnce()>(func: F) -> Option<usize> {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    func();
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    Some(after - before).filter(|_| enabled())
+}
@@ -0,0 +1,905 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use once_cell::sync::Lazy;
+
+use crate::grid;
+use crate::rules::{Rules, TieBreak, WonFields};
+use crate::zobrist;
+
+/// Index of a local field or of a square within it, `0..9` in row-major order.
+pub type Index = u8;
+/// Set of squares of a 3x3 field (or of fields of the meta-board), bit `i` is index `i`.
+pub type Bits = u16;
+
+/// Bitmasks of the 8 winning lines of a 3x3 field.
+pub const WIN: [Bits; 8] = [0o421, 0o124, 0o700, 0o070, 0o007, 0o111, 0o222, 0o444];
+
+// the lines of the 3x3 `GenericBoard`, in another order
+const _: () = {
+    let lines = grid::win_lines(3);
+    let mut i = 0;
+    while i < 8 {
+        let mut j = 0;
+        while j < 8 && WIN[j] as u32 != lines[i] {
+            j += 1;
+        }
+        assert!(j < 8, "WIN must hold the lines of a 3x3 grid");
+        i += 1;
+    }
+};
+/// Bitmask of all 9 squares of a field.
+pub const ALL_FIELDS: Bits = 0o777;
+
+pub static IS_WON: Lazy<Vec<bool>> = Lazy::new(|| {
+    (0..1024)
+        .map(|field| WIN.iter().any(|w| field & w == *w))
+        .collect()
+});
+
+const fn lines_hit() -> [u8; 512] {
+    let mut table = [0; 512];
+    let mut field = 0;
+    while field < 512 {
+        let mut i = 0;
+        while i < WIN.len() {
+            if field as Bits & WIN[i] != 0 {
+                table[field] |= 1 << i;
+            }
+            i += 1;
+        }
+        field += 1;
+    }
+    table
+}
+
+// the lines of `WIN` that a set of stones touches, as a bitmask
+static LINES_HIT: [u8; 512] = lines_hit();
+
+/// A square on the board: a local field and a square within it.
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pos {
+    pub(crate) field: Index,
+    // one-hot square bitmask
+    pub(crate) square: Bits,
+}
+
+impl Pos {
+    // placeholder with an empty square, never a legal move
+    pub(crate) const NULL: Pos = Pos {
+        field: 0,
+        square: 0,
+    };
+
+    /// Position of `square` within local `field`, or `None` if either is not in `0..9`.
+    pub fn new(field: Index, square: Index) -> Option<Pos> {
+        if field < 9 && square < 9 {
+            Some(Pos {
+                field,
+                square: 1 << square,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Local field index, `0..9`.
+    pub fn field(self) -> Index {
+        self.field
+    }
+
+    /// Square index within the local field, `0..9`.
+    pub fn square(self) -> Index {
+        self.square.trailing_zeros() as Index
+    }
+
+    /// Square as a one-hot bitmask within the local field.
+    pub fn square_bit(self) -> Bits {
+        self.square
+    }
+
+    /// Compact index `field * 9 + square`, `0..81`.
+    pub fn to_index(self) -> u8 {
+        self.field * 9 + self.square()
+    }
+
+    /// Inverse of [`Pos::to_index`], or `None` if `index` is not in `0..81`.
+    pub fn from_index(index: u8) -> Option<Pos> {
+        if index < 81 {
+            Pos::new(index / 9, index % 9)
+        } else {
+            None
+        }
+    }
+}
+
+/// An optional [`Pos`] packed into a single byte, for tables and records.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move8(u8);
+
+impl Move8 {
+    pub const NONE: Move8 = Move8(u8::MAX);
+
+    /// The encoded byte: [`Pos::to_index`], or 255 for [`Move8::NONE`].
+    pub fn to_u8(self) -> u8 {
+        self.0
+    }
+
+    /// Decodes a byte from [`Move8::to_u8`], `None` if it is neither.
+    pub fn from_u8(byte: u8) -> Option<Move8> {
+        if byte < 81 || byte == u8::MAX {
+            Some(Move8(byte))
+        } else {
+            None
+        }
+    }
+
+    pub fn pos(self) -> Option<Pos> {
+        Pos::from_index(self.0)
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Move8::NONE
+    }
+
+    /// `self`, or `other` if `self` is [`Move8::NONE`].
+    pub fn or(self, other: Move8) -> Move8 {
+        if self.is_none() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl Default for Move8 {
+    fn default() -> Self {
+        Move8::NONE
+    }
+}
+
+impl From<Pos> for Move8 {
+    fn from(pos: Pos) -> Self {
+        Move8(pos.to_index())
+    }
+}
+
+impl From<Option<Pos>> for Move8 {
+    fn from(pos: Option<Pos>) -> Self {
+        pos.map_or(Move8::NONE, Move8::from)
+    }
+}
+
+impl From<Move8> for Option<Pos> {
+    fn from(mov: Move8) -> Self {
+        mov.pos()
+    }
+}
+
+/// A move together with the state needed to undo it.
+///
+/// Produced by move generation and returned by [`Bitboard::make_move`] and
+/// [`Bitboard::try_make_move`]; pass it back to [`Bitboard::undo_move`].
+#[repr(C, packed)]
+#[derive(Copy, Clone)]
+pub struct Move {
+    pub(crate) pos: Pos,
+    pub(crate) all_valid: bool,
+    pub(crate) field_status: FieldStatus,
+    pub(crate) meta_field: Bits,
+    pub(crate) n_blocked: u8,
+    pub(crate) last_move: Pos,
+    // low half of the hash of the position the move is made in, to tell a
+    // stale move
+    pub(crate) hash: u32,
+}
+
+impl Move {
+    pub(crate) const NULL: Move = Move {
+        pos: Pos::NULL,
+        all_valid: true,
+        field_status: FieldStatus::None,
+        meta_field: 0,
+        n_blocked: 0,
+        last_move: Pos::NULL,
+        hash: 0,
+    };
+
+    pub fn pos(&self) -> Pos {
+        self.pos
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Move").field(&self.pos()).finish()
+    }
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub enum Player {
+    #[default]
+    X = 0,
+    O = 1,
+}
+
+impl Player {
+    pub const ALL: [Player; 2] = [Player::X, Player::O];
+
+    pub fn other(self) -> Player {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> Option<Player> {
+        match index {
+            0 => Some(Player::X),
+            1 => Some(Player::O),
+            _ => None,
+        }
+    }
+}
+
+impl From<Player> for usize {
+    fn from(p: Player) -> usize {
+        p.index()
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Player::X => "X",
+            Player::O => "O",
+        })
+    }
+}
+
+/// Why a move is not legal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    GameOver,
+    /// The previous move sent the player to another field.
+    WrongField {
+        forced: Index,
+    },
+    /// The field is full, or already won and the rules close won fields.
+    BlockedField,
+    Occupied,
+    /// The field or square does not exist on the board.
+    OutOfRange,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "the game is over"),
+            MoveError::WrongField { forced } => write!(f, "must play in field {}", forced + 1),
+            MoveError::BlockedField => write!(f, "the field takes no more stones"),
+            MoveError::Occupied => write!(f, "the square is taken"),
+            MoveError::OutOfRange => write!(f, "no such square on the board"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum FieldStatus {
+    Won0 = 0,
+    Won1 = 1,
+    Tied,
+    #[default]
+    None,
+}
+
+impl FieldStatus {
+    pub fn blocked(self) -> bool {
+        self != FieldStatus::None
+    }
+
+    pub fn won_by(p: Player) -> FieldStatus {
+        match p {
+            Player::X => FieldStatus::Won0,
+            Player::O => FieldStatus::Won1,
+        }
+    }
+
+    pub fn won(self, p: Player) -> bool {
+        self as u8 == p as u8
+    }
+
+    pub fn winner(self) -> Option<Player> {
+        match self {
+            FieldStatus::Won0 => Some(Player::X),
+            FieldStatus::Won1 => Some(Player::O),
+            _ => None,
+        }
+    }
+}
+
+/// How a finished game ended, see [`Bitboard::result`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    Win(Player),
+    /// Every field is decided, neither player has a meta line and the
+    /// tie-break of the rules, if any, does not separate them.
+    Tie,
+}
+
+impl GameResult {
+    pub fn winner(self) -> Option<Player> {
+        match self {
+            GameResult::Win(p) => Some(p),
+            GameResult::Tie => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Win(p) => write!(f, "{} wins", p),
+            GameResult::Tie => write!(f, "tie"),
+        }
+    }
+}
+
+/// A single difference between two positions, see [`Bitboard::diff`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellChange {
+    Added {
+        player: Player,
+        pos: Pos,
+    },
+    Removed {
+        player: Player,
+        pos: Pos,
+    },
+    Status {
+        field: Index,
+        from: FieldStatus,
+        to: FieldStatus,
+    },
+}
+
+/// Ultimate tic-tac-toe position with incremental make/undo.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Bitboard {
+    pub(crate) valid_field: Option<Index>,
+    pub(crate) board: [[Bits; 9]; 2],
+    pub(crate) turn: Player,
+    pub(crate) field_status: [FieldStatus; 9],
+    pub(crate) meta_field: [Bits; 2],
+    pub(crate) game_over: bool,
+    pub(crate) n_blocked: u8,
+    pub(crate) last_move: Pos,
+    pub(crate) hash: u64,
+    pub(crate) rules: Rules,
+}
+
+impl Default for Bitboard {
+    fn default() -> Self {
+        Bitboard {
+            valid_field: None,
+            board: [[0; 9]; 2],
+            turn: Player::X,
+            field_status: [FieldStatus::None; 9],
+            meta_field: [0; 2],
+            game_over: false,
+            n_blocked: 0,
+            last_move: Pos::NULL,
+            hash: 0,
+            rules: Rules::default(),
+        }
+    }
+}
+
+impl Bitboard {
+    /// The empty starting position, X to move anywhere.
+    pub fn new() -> Bitboard {
+        Bitboard::default()
+    }
+
+    /// The starting position of a game played under `rules`.
+    pub fn with_rules(rules: Rules) -> Bitboard {
+        Bitboard {
+            rules,
+            ..Bitboard::default()
+        }
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub(crate) fn get(&self, p: Player, field: Index) -> Bits {
+        let f = field as usize;
+        unsafe { *self.board.get_unchecked(p.index()).get_unchecked(f) }
+    }
+
+    pub(crate) fn get_mut(&mut self, p: Player, field: Index) -> &mut Bits {
+        let f = field as usize;
+        unsafe { self.board.get_unchecked_mut(p.index()).get_unchecked_mut(f) }
+    }
+
+    pub(crate) fn get_fields(&self, field: Index) -> (Bits, Bits) {
+        (self.get(Player::X, field), self.get(Player::O, field))
+    }
+
+    pub(crate) fn get_field_status(&self, field: Index) -> FieldStatus {
+        unsafe { *self.field_status.get_unchecked(field as usize) }
+    }
+
+    pub(crate) fn get_meta_field(&self, p: Player) -> Bits {
+        unsafe { *self.meta_field.get_unchecked(p.index()) }
+    }
+
+    /// Whether `field` takes no more stones: decided, or under
+    /// [`WonFields::Playable`] full.
+    #[inline(always)]
+    pub(crate) fn is_closed(&self, field: Index) -> bool {
+        grid::is_closed(self.get_field_status(field), self.rules.won_fields, || {
+            self.is_full(field)
+        })
+    }
+
+    fn is_full(&self, field: Index) -> bool {
+        let (white, black) = self.get_fields(field);
+        white | black == ALL_FIELDS
+    }
+
+    fn set_field_status(&mut self, field: Index, status: FieldStatus) {
+        unsafe { *self.field_status.get_unchecked_mut(field as usize) = status };
+    }
+
+    fn set_meta_field(&mut self, p: Player, meta_field: Bits) {
+        unsafe { *self.meta_field.get_unchecked_mut(p.index()) = meta_field };
+    }
+
+    /// Whether `pos` is a legal move for the side to move.
+    pub fn is_legal(&self, pos: Pos) -> bool {
+        self.check_move(pos).is_ok()
+    }
+
+    /// Why `pos` is not a legal move for the side to move, if it is not.
+    pub fn check_move(&self, pos: Pos) -> Result<(), MoveError> {
+        let (white, black) = self.get_fields(pos.field);
+        if self.game_over {
+            Err(MoveError::GameOver)
+        } else if let Some(forced) = self.valid_field.filter(|&f| f != pos.field) {
+            Err(MoveError::WrongField { forced })
+        } else if self.is_closed(pos.field) {
+            Err(MoveError::BlockedField)
+        } else if (white | black) & pos.square != 0 {
+            Err(MoveError::Occupied)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Plays `pos` for the side to move, returning the move to undo it with.
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is not a legal move, see [`Bitboard::try_make_move`].
+    pub fn make_move(&mut self, pos: Pos) -> Move {
+        match self.try_make_move(pos) {
+            Ok(mov) => mov,
+            Err(err) => panic!("illegal move {}: {}", pos, err),
+        }
+    }
+
+    /// Plays `pos` if it is legal, returning the move to undo it with.
+    pub fn try_make_move(&mut self, pos: Pos) -> Result<Move, MoveError> {
+        self.check_move(pos)?;
+        // what move generation would have produced for `pos`
+        let mov = Move {
+            pos,
+            all_valid: self.valid_field.is_none(),
+            field_status: self.get_field_status(pos.field),
+            meta_field: self.get_meta_field(self.turn),
+            n_blocked: self.n_blocked,
+            last_move: self.last_move,
+            hash: self.hash as u32,
+        };
+        // SAFETY: checked above
+        unsafe { self.make_move_unchecked(pos) };
+        Ok(mov)
+    }
+
+    /// Takes back `mov`, which must be the last move made on this board.
+    ///
+    /// # Panics
+    ///
+    /// If `mov` is not the last move made, e.g. a stale [`Move`] kept from
+    /// an earlier position, or its square is not occupied by the player who
+    /// moved last; the board is left as it was.
+    pub fn undo_move(&mut self, mov: &Move) {
+        let pos = mov.pos;
+        assert!(
+            self.last_move == pos,
+            "undo of {} which is not the last move made{}",
+            pos,
+            self.last_move()
+                .map_or(String::new(), |last| format!(" ({})", last))
+        );
+        assert!(
+            self.get(self.turn.other(), pos.field) & pos.square != 0,
+            "undo of a move that was not played: {}",
+            pos
+        );
+        let mut before = *self;
+        unsafe { before.undo_move_unchecked(mov) };
+        let hash = mov.hash;
+        assert!(
+            before.hash as u32 == hash,
+            "undo of {} which was made in another position",
+            pos
+        );
+        *self = before;
+    }
+
+    /// # Safety
+    ///
+    /// `pos` must be legal in this position; the unchecked indexing used
+    /// throughout relies on the invariants a legal move preserves.
+    #[inline(always)]
+    pub(crate) unsafe fn make_move_unchecked(&mut self, pos: Pos) {
+        let square = self.get_mut(self.turn, pos.field);
+        *square |= pos.square;
+        let square = *square;
+        // under `WonFields::Playable` the field may have been won before,
+        // which settles it for good
+        if is_won(square) && !self.get_field_status(pos.field).blocked() {
+            self.set_field_status(pos.field, FieldStatus::won_by(self.turn));
+            if self.rules.won_fields == WonFields::Playable {
+                self.hash ^= zobrist::won(self.turn, pos.field);
+            }
+            let meta = self.get_meta_field(self.turn) | (1 << pos.field as Bits);
+            self.set_meta_field(self.turn, meta);
+            self.n_blocked += 1;
+            if self.n_blocked == 9 || is_won(meta) {
+                self.game_over = is_won(meta) || self.no_room_left();
+            }
+        } else {
+            let other = self.get(self.turn.other(), pos.field);
+            if is_tied(square | other) {
+                if !self.get_field_status(pos.field).blocked() {
+                    self.set_field_status(pos.field, FieldStatus::Tied);
+                    self.n_blocked += 1;
+                }
+                if self.n_blocked == 9 {
+                    self.game_over = self.no_room_left();
+                }
+            }
+        }
+        let next = pos.square.trailing_zeros() as Index;
+        let valid_field = if self.is_closed(next) {
+            None
+        } else {
+            Some(next)
+        };
+        self.hash ^= zobrist::stone(self.turn, pos.field, next)
+            ^ zobrist::forced(self.valid_field)
+            ^ zobrist::forced(valid_field)
+            ^ zobrist::KEYS.side;
+        self.valid_field = valid_field;
+        self.last_move = pos;
+        self.turn = self.turn.other();
+    }
+
+    /// # Safety
+    ///
+    /// `mov` must have been generated for the position before the last
+    /// move and be that move.
+    pub(crate) unsafe fn undo_move_unchecked(&mut self, mov: &Move) {
+        let pos = mov.pos;
+        self.turn = self.turn.other();
+        *self.get_mut(self.turn, pos.field) &= !pos.square;
+        let valid_field = if mov.all_valid { None } else { Some(pos.field) };
+        self.hash ^= zobrist::stone(self.turn, pos.field, pos.square.trailing_zeros() as Index)
+            ^ zobrist::forced(self.valid_field)
+            ^ zobrist::forced(valid_field)
+            ^ zobrist::KEYS.side;
+        self.valid_field = valid_field;
+        if self.rules.won_fields == WonFields::Playable
+            && mov.field_status != self.get_field_status(pos.field)
+        {
+            if let Some(winner) = self.get_field_status(pos.field).winner() {
+                self.hash ^= zobrist::won(winner, pos.field);
+            }
+        }
+        self.set_field_status(pos.field, mov.field_status);
+        self.set_meta_field(self.turn, mov.meta_field);
+        self.n_blocked = mov.n_blocked;
+        self.last_move = mov.last_move;
+        self.game_over = false;
+    }
+
+    // with every field decided, whether the rules leave nowhere to play
+    #[inline(always)]
+    fn no_room_left(&self) -> bool {
+        self.rules.won_fields == WonFields::Closed
+            || self.board[0]
+                .iter()
+                .zip(&self.board[1])
+                .all(|(white, black)| white | black == ALL_FIELDS)
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.game_over
+    }
+
+    /// The player who completed a meta line, or who won the game on the
+    /// [`TieBreak`] of the rules; `None` while the game is on or if it ended
+    /// in a tie.
+    pub fn winner(&self) -> Option<Player> {
+        let line = Player::ALL
+            .iter()
+            .copied()
+            .find(|&p| is_won(self.get_meta_field(p)));
+        if line.is_some() || !self.game_over {
+            return line;
+        }
+        match self.rules.tie_break {
+            TieBreak::None => None,
+            TieBreak::FieldsWon => {
+                let won = |p: Player| self.get_meta_field(p).count_ones();
+                match won(Player::X).cmp(&won(Player::O)) {
+                    Ordering::Greater => Some(Player::X),
+                    Ordering::Less => Some(Player::O),
+                    Ordering::Equal => None,
+                }
+            }
+        }
+    }
+
+    /// `None` while the game is on.
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.game_over {
+            return None;
+        }
+        Some(self.winner().map_or(GameResult::Tie, GameResult::Win))
+    }
+
+    /// The fields of the meta line that won the game, as a mask from [`WIN`];
+    /// `None` for a game won on the tie-break.
+    pub fn winning_line(&self) -> Option<Bits> {
+        let meta = self.get_meta_field(self.winner()?);
+        WIN.iter().copied().find(|&line| meta & line == line)
+    }
+
+    /// Undecided local fields that neither player can win any more, as a
+    /// meta-board bitmask; they can only end up tied.
+    pub fn dead_fields(&self) -> Bits {
+        (0..9)
+            .filter(|&field| {
+                let (white, black) = self.get_fields(field);
+                !self.get_field_status(field).blocked() && is_dead(white, black)
+            })
+            .fold(0, |acc, field| acc | (1 << field))
+    }
+
+    /// The result the game will have however it is played on, when the
+    /// meta board alone settles it: no player can complete a meta line any
+    /// more, and the tie-break of the rules, if any, cannot change hands.
+    /// Cheap and conservative; `None` says nothing about the position.
+    pub fn decided_result(&self) -> Option<GameResult> {
+        self.decided_result_with(self.dead_fields())
+    }
+
+    // `decided_result` for the given `dead_fields`, to share them with eval
+    pub(crate) fn decided_result_with(&self, dead_fields: Bits) -> Option<GameResult> {
+        if self.game_over {
+            return self.result();
+        }
+        // dead fields count as decided: they will be tied
+        let decided = (0..9)
+            .filter(|&field| self.get_field_status(field).blocked())
+            .fold(dead_fields, |acc, field| acc | (1 << field));
+        let can_win = |p: Player| {
+            let closed = decided & !self.get_meta_field(p);
+            WIN.iter().any(|&line| line & closed == 0)
+        };
+        if can_win(Player::X) || can_win(Player::O) {
+            return None;
+        }
+        match self.rules.tie_break {
+            TieBreak::None => Some(GameResult::Tie),
+            TieBreak::FieldsWon => {
+                let won = |p: Player| self.get_meta_field(p).count_ones();
+                let (x, o) = (won(Player::X), won(Player::O));
+                let open = 9 - decided.count_ones();
+                if x > o + open {
+                    Some(GameResult::Win(Player::X))
+                } else if o > x + open {
+                    Some(GameResult::Win(Player::O))
+                } else if open == 0 && x == o {
+                    Some(GameResult::Tie)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Zobrist key of the position, maintained incrementally by make/undo.
+    /// Under [`WonFields::Playable`] it also covers who won each field
+    /// first, which the stones alone do not settle.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Zobrist key recomputed from scratch; always equal to [`Bitboard::hash`].
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::forced(self.valid_field);
+        if self.turn == Player::O {
+            hash ^= zobrist::KEYS.side;
+        }
+        for &p in &Player::ALL {
+            for field in 0..9 {
+                for square in 0..9 {
+                    if self.get(p, field) & (1 << square) != 0 {
+                        hash ^= zobrist::stone(p, field, square);
+                    }
+                }
+            }
+        }
+        if self.rules.won_fields == WonFields::Playable {
+            for field in 0..9 {
+                if let Some(p) = self.get_field_status(field).winner() {
+                    hash ^= zobrist::won(p, field);
+                }
+            }
+        }
+        hash
+    }
+
+    /// Squares of local `field` (`0..9`) occupied by `p`.
+    pub fn stones(&self, p: Player, field: Index) -> Bits {
+        self.board[p.index()][field as usize]
+    }
+
+    /// Status of local `field` (`0..9`).
+    pub fn field_status(&self, field: Index) -> FieldStatus {
+        self.field_status[field as usize]
+    }
+
+    /// Local fields won by `p`, as a meta-board bitmask.
+    pub fn meta_field(&self, p: Player) -> Bits {
+        self.meta_field[p.index()]
+    }
+
+    /// Playable squares of every local field; all empty once the game is over.
+    pub fn legal_mask(&self) -> [Bits; 9] {
+        let mut mask = [0; 9];
+        if self.game_over {
+            return mask;
+        }
+        let available_fields = match self.valid_field {
+            Some(field) => field..field + 1,
+            _ => 0..9,
+        };
+        for field in available_fields {
+            let f = field as usize;
+            if !self.is_closed(field) {
+                let (white, black) = self.get_fields(field);
+                mask[f] = !(white | black) & ALL_FIELDS;
+            }
+        }
+        mask
+    }
+
+    /// Stones added or removed and field statuses changed going from `self` to `other`.
+    pub fn diff(&self, other: &Bitboard) -> Vec<CellChange> {
+        let mut changes = Vec::new();
+        for field in 0..9 {
+            for &player in &Player::ALL {
+                let (old, new) = (self.get(player, field), other.get(player, field));
+                for square in 0..9 {
+                    let square = 1 << square;
+                    let pos = Pos { field, square };
+                    if new & !old & square != 0 {
+                        changes.push(CellChange::Added { player, pos });
+                    } else if old & !new & square != 0 {
+                        changes.push(CellChange::Removed { player, pos });
+                    }
+                }
+            }
+            let (from, to) = (
+                self.field_status[field as usize],
+                other.field_status[field as usize],
+            );
+            if from != to {
+                changes.push(CellChange::Status { field, from, to });
+            }
+        }
+        changes
+    }
+
+    /// Number of moves played so far.
+    pub fn ply(&self) -> usize {
+        self.board
+            .iter()
+            .flatten()
+            .map(|b| b.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn side_to_move(&self) -> Player {
+        self.turn
+    }
+
+    /// The local field the side to move must play in, or `None` if free to play anywhere.
+    pub fn forced_field(&self) -> Option<Index> {
+        self.valid_field
+    }
+
+    /// Number of local fields that are won or tied.
+    pub fn blocked_count(&self) -> u8 {
+        self.n_blocked
+    }
+
+    pub fn last_move(&self) -> Option<Pos> {
+        Some(self.last_move).filter(|pos| pos.square != 0)
+    }
+
+    #[cfg(feature = "check-undo")]
+    pub(crate) fn assert_restored(&self, after: &Bitboard, pos: Pos) {
+        let mut diff = Vec::new();
+        macro_rules! check {
+            ($($name:ident),*) => {
+                $(if self.$name != after.$name {
+                    diff.push(format!(
+                        "  {}: {:?} != {:?}",
+                        stringify!($name),
+                        self.$name,
+                        after.$name
+                    ));
+                })*
+            };
+        }
+        check!(
+            valid_field,
+            board,
+            turn,
+            field_status,
+            meta_field,
+            game_over,
+            n_blocked,
+            last_move,
+            hash
+        );
+        if !diff.is_empty() {
+            panic!(
+                "undo of {} did not restore the position\n{}",
+                pos,
+                diff.join("\n")
+            );
+        }
+    }
+}
+
+pub fn is_tied(field: Bits) -> bool {
+    field == ALL_FIELDS
+}
+
+/// Whether neither player can complete a line in a field with these stones.
+pub fn is_dead(white: Bits, black: Bits) -> bool {
+    let hit = |field: Bits| unsafe { *LINES_HIT.get_unchecked((field & ALL_FIELDS) as usize) };
+    hit(white) & hit(black) == u8::MAX
+}
+
+pub fn is_won(field: Bits) -> bool {
+    unsafe { *IS_WON.get_unchecked((field & ALL_FIELDS) as usize) }
+}
@@ -0,0 +1,265 @@
+//! Opening book: moves worth playing in early positions, built from searches
+//! or played games and kept in a compact binary file.
+//!
+//! Positions are keyed by [`Bitboard::canonical_hash`], so the book covers
+//! all symmetric transforms of a position with one entry; moves are stored
+//! as played in the canonical position and mapped back when probing.
+//!
+//! The file is `UTTTBOOK`, the format version (`u16`) and the number of
+//! entries (`u32`), then 13 bytes per entry: key (`u64`), move ([`Move8`]),
+//! weight (`u16`) and score (`i16`), sorted by key and move, and finally an
+//! FNV-1a checksum (`u64`) of the entry bytes. All numbers are
+//! little-endian.
+
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::board::{Bitboard, Move8, Pos};
+use crate::cancel::{CancelToken, Cancelled};
+use crate::eval::Score;
+use crate::search::Searcher;
+use crate::symmetry::inverse;
+
+pub(crate) const MAGIC: &[u8; 8] = b"UTTTBOOK";
+pub(crate) const VERSION: u16 = 1;
+const ENTRY_BYTES: usize = 13;
+// entries reserved up front, whatever the header claims
+const MAX_RESERVE: usize = 1 << 16;
+
+// FNV-1a, like `search::Signature`
+const CHECKSUM_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn checksum(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// A book move for a position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    pub pos: Pos,
+    /// How often the move was added: searches add 1, games 1 per game won
+    /// or tied with it.
+    pub weight: u16,
+    /// Search score for the side to move, 0 if the move was only played.
+    pub score: Score,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    mov: Move8,
+    weight: u16,
+    score: i16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    // sorted by key, then move
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    pub fn new() -> Book {
+        Book::default()
+    }
+
+    /// Number of moves in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds `weight` to move `pos` in `board`, and sets its score if one is
+    /// given.
+    pub fn add(&mut self, board: &Bitboard, pos: Pos, weight: u16, score: Option<Score>) {
+        let sym = board.canonical_symmetry();
+        let key = board.transformed(sym).hash();
+        let mov = Move8::from(pos.transformed(sym));
+        let score = score.map(|s| s.clamp(i16::MIN as Score, i16::MAX as Score) as i16);
+        match self
+            .entries
+            .binary_search_by_key(&(key, mov.to_u8()), |e| (e.key, e.mov.to_u8()))
+        {
+            Ok(i) => {
+                let entry = &mut self.entries[i];
+                entry.weight = entry.weight.saturating_add(weight);
+                entry.score = score.unwrap_or(entry.score);
+            }
+            Err(i) => self.entries.insert(
+                i,
+                Entry {
+                    key,
+                    mov,
+                    weight,
+                    score: score.unwrap_or(0),
+                },
+            ),
+        }
+    }
+
+    /// Adds the first `plies` moves of a game played from `start` that were
+    /// made by the winner, or by both sides if it is not won.
+    pub fn add_game(&mut self, start: Bitboard, moves: &[Pos], plies: usize) {
+        let mut end = start;
+        for &pos in moves {
+            end.make_move(pos);
+        }
+        let winner = end.result().or_else(|| end.decided_result());
+        let winner = winner.and_then(|result| result.winner());
+        let mut board = start;
+        for &pos in moves.iter().take(plies) {
+            if winner.is_none_or(|p| p == board.side_to_move()) {
+                self.add(&board, pos, 1, None);
+            }
+            board.make_move(pos);
+        }
+    }
+
+    /// Book moves for `board`, heaviest first and then by score.
+    pub fn moves(&self, board: &Bitboard) -> Vec<BookMove> {
+        let sym = board.canonical_symmetry();
+        let key = board.transformed(sym).hash();
+        let start = self.entries.partition_point(|e| e.key < key);
+        let mut moves: Vec<BookMove> = self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .filter_map(|e| {
+                let pos = e.mov.pos()?.transformed(inverse(sym));
+                Some(BookMove {
+                    pos,
+                    weight: e.weight,
+                    score: e.score as Score,
+                })
+            })
+            .filter(|m| board.is_legal(m.pos))
+            .collect();
+        moves.sort_by_key(|m| (std::cmp::Reverse(m.weight), -m.score));
+        moves
+    }
+
+    /// The move to play from the book, `None` if `board` is not in it.
+    pub fn probe(&self, board: &Bitboard) -> Option<Pos> {
+        self.moves(board).first().map(|m| m.pos)
+    }
+
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let len = u32::try_from(self.entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "opening book too large"))?;
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        let mut hash = CHECKSUM_SEED;
+        for e in &self.entries {
+            let mut bytes = [0; ENTRY_BYTES];
+            bytes[..8].copy_from_slice(&e.key.to_le_bytes());
+            bytes[8] = e.mov.to_u8();
+            bytes[9..11].copy_from_slice(&e.weight.to_le_bytes());
+            bytes[11..].copy_from_slice(&e.score.to_le_bytes());
+            hash = checksum(hash, &bytes);
+            out.write_all(&bytes)?;
+        }
+        out.write_all(&hash.to_le_bytes())?;
+        out.flush()
+    }
+
+    /// Reads a book written by [`Book::write_to`], rejecting other versions,
+    /// truncated files and checksum mismatches.
+    pub fn read_from<R: Read>(mut input: R) -> io::Result<Book> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut read = |buf: &mut [u8]| {
+            input.read_exact(buf).map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => invalid("truncated opening book"),
+                _ => err,
+            })
+        };
+        let mut header = [0; 14];
+        read(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("not an opening book"));
+        }
+        if u16::from_le_bytes([header[8], header[9]]) != VERSION {
+            return Err(invalid("unsupported opening book version"));
+        }
+        let len = u32::from_le_bytes(header[10..].try_into().expect("4 bytes")) as usize;
+        let mut entries = Vec::with_capacity(len.min(MAX_RESERVE));
+        let mut bytes = [0; ENTRY_BYTES];
+        let mut hash = CHECKSUM_SEED;
+        for _ in 0..len {
+            read(&mut bytes)?;
+            hash = checksum(hash, &bytes);
+            let mov = Move8::from_u8(bytes[8])
+                .filter(|m| !m.is_none())
+                .ok_or_else(|| invalid("invalid move in opening book"))?;
+            entries.push(Entry {
+                key: u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes")),
+                mov,
+                weight: u16::from_le_bytes([bytes[9], bytes[10]]),
+                score: i16::from_le_bytes([bytes[11], bytes[12]]),
+            });
+        }
+        let mut stored = [0; 8];
+        read(&mut stored)?;
+        if u64::from_le_bytes(stored) != hash {
+            return Err(invalid("opening book checksum mismatch"));
+        }
+        entries.sort_by_key(|e| (e.key, e.mov.to_u8()));
+        Ok(Book { entries })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Book::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Searches every position up to `plies` moves after `start` to `depth` and
+/// adds its best move. Symmetric positions are searched once; returns the
+/// number of positions searched. Gives up once `cancel` fires, keeping the
+/// moves added so far; give `searcher` the same token to cut its search
+/// short too.
+pub fn add_searches(
+    book: &mut Book,
+    searcher: &mut Searcher,
+    start: &Bitboard,
+    plies: usize,
+    depth: u8,
+    cancel: &CancelToken,
+) -> Result<usize, Cancelled> {
+    let mut seen = HashSet::new();
+    let mut frontier = vec![*start];
+    for ply in 0..=plies {
+        let mut next = Vec::new();
+        for board in frontier {
+            if board.game_over() || !seen.insert(board.canonical_hash()) {
+                continue;
+            }
+            let result = searcher.search(&board, depth);
+            if cancel.is_cancelled() {
+                return Err(Cancelled);
+            }
+            if let Some(best) = result.best {
+                book.add(&board, best, 1, Some(result.score));
+            }
+            if ply < plies {
+                next.extend(board.moves().map(|pos| {
+                    let mut child = board;
+                    child.make_move(pos);
+                    child
+                }));
+            }
+        }
+        frontier = next;
+    }
+    Ok(seen.len())
+}
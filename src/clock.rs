@@ -0,0 +1,119 @@
+//! Chess-style game clocks: base time, increment and simple delay, with
+//! the engine's share of the remaining time per move.
+
+use std::time::{Duration, Instant};
+
+use crate::board::Player;
+
+/// Time to spend on one move given the clock of the side to move.
+pub fn allot_time(remaining: Duration, increment: Duration) -> Duration {
+    (remaining / 20 + increment * 3 / 4).min(remaining / 2)
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    /// Simple delay: the first `delay` of every move is not charged.
+    pub delay: Duration,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration, increment: Duration) -> TimeControl {
+        TimeControl {
+            base,
+            increment,
+            delay: Duration::default(),
+        }
+    }
+
+    pub fn with_delay(self, delay: Duration) -> TimeControl {
+        TimeControl { delay, ..self }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    running: Option<(Player, Instant)>,
+    flagged: Option<Player>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            control,
+            remaining: [control.base; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub fn running(&self) -> Option<Player> {
+        self.running.map(|(p, _)| p)
+    }
+
+    pub fn flagged(&self) -> Option<Player> {
+        self.flagged.or_else(|| {
+            let (p, _) = self.running?;
+            Some(p).filter(|&p| self.remaining(p) == Duration::default())
+        })
+    }
+
+    /// Remaining time for `p`, including the time spent on a move in progress.
+    pub fn remaining(&self, p: Player) -> Duration {
+        let remaining = self.remaining[p.index()];
+        match self.running {
+            Some((q, start)) if q == p => remaining.saturating_sub(self.charged(start.elapsed())),
+            _ => remaining,
+        }
+    }
+
+    pub fn start(&mut self, p: Player) {
+        self.stop();
+        self.running = Some((p, Instant::now()));
+    }
+
+    /// Stops the running clock, charging the move and adding the increment;
+    /// returns the wall time spent on the move.
+    pub fn stop(&mut self) -> Option<Duration> {
+        let (p, start) = self.running.take()?;
+        let elapsed = start.elapsed();
+        let charged = self.charged(elapsed);
+        let remaining = &mut self.remaining[p.index()];
+        if charged >= *remaining {
+            *remaining = Duration::default();
+            self.flagged.get_or_insert(p);
+        } else {
+            *remaining = *remaining - charged + self.control.increment;
+        }
+        Some(elapsed)
+    }
+
+    /// Ends the current player's move and starts the opponent's clock.
+    pub fn press(&mut self) -> Option<Duration> {
+        let (p, _) = self.running?;
+        let elapsed = self.stop();
+        self.running = Some((p.other(), Instant::now()));
+        elapsed
+    }
+
+    /// Time to spend on the next move of `p`: [`allot_time`] of what is
+    /// left, plus the delay, which is free.
+    pub fn allot(&self, p: Player) -> Duration {
+        allot_time(self.remaining(p), self.control.increment) + self.control.delay
+    }
+
+    pub fn set_remaining(&mut self, p: Player, remaining: Duration) {
+        self.remaining[p.index()] = remaining;
+    }
+
+    fn charged(&self, elapsed: Duration) -> Duration {
+        elapsed.saturating_sub(self.control.delay)
+    }
+}
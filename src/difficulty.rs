@@ -0,0 +1,116 @@
+//! How hard a position is to play, judged by how unsettled the engine's
+//! opinion of it is: for picking puzzles and test positions, or for spending
+//! more time on critical moves.
+
+use crate::board::Bitboard;
+use crate::cancel::{CancelToken, Cancelled};
+use crate::eval::Score;
+use crate::mcts::{self, MctsConfig, MctsEngine};
+use crate::search::{SearchLimits, SearchResult, Searcher};
+
+// scores are clamped to this before measuring volatility, so that a search
+// discovering a forced win does not swamp everything else
+const SCORE_CLAMP: Score = 1000;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Difficulty {
+    /// The shallowest and the deepest iteration prefer different moves.
+    pub shallow_deep_disagree: bool,
+    /// Fraction of iterations whose best move differs from the previous one.
+    pub best_move_changes: f64,
+    /// Standard deviation of the score across iterations.
+    pub score_volatility: f64,
+    /// Entropy of the MCTS root visits relative to a uniform spread over the
+    /// legal moves, in `[0, 1]`; zero without an MCTS search.
+    pub policy_entropy: f64,
+}
+
+impl Difficulty {
+    /// From the iterations of one alpha-beta search, as passed to
+    /// [`Searcher::think_with`], and optionally the root of an MCTS search
+    /// of the same position.
+    pub fn from_search(
+        iterations: &[SearchResult],
+        mcts: Option<&mcts::SearchResult>,
+    ) -> Difficulty {
+        let mut difficulty = Difficulty::default();
+        if let (Some(first), Some(last)) = (iterations.first(), iterations.last()) {
+            difficulty.shallow_deep_disagree = first.best != last.best;
+            let changes = iterations
+                .windows(2)
+                .filter(|w| w[0].best != w[1].best)
+                .count();
+            difficulty.best_move_changes = changes as f64 / (iterations.len() - 1).max(1) as f64;
+            let scores: Vec<f64> = iterations
+                .iter()
+                .map(|r| r.score.clamp(-SCORE_CLAMP, SCORE_CLAMP) as f64)
+                .collect();
+            let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+            let variance =
+                scores.iter().map(|s| (s - mean) * (s - mean)).sum::<f64>() / scores.len() as f64;
+            difficulty.score_volatility = variance.sqrt();
+        }
+        if let Some(result) = mcts {
+            difficulty.policy_entropy = normalized_entropy(result);
+        }
+        difficulty
+    }
+
+    /// All measures folded into `[0, 1]`, higher is harder.
+    pub fn combined(&self) -> f64 {
+        let disagree = if self.shallow_deep_disagree { 1.0 } else { 0.0 };
+        let volatility = (self.score_volatility / 100.0).tanh();
+        0.35 * disagree
+            + 0.25 * self.best_move_changes
+            + 0.2 * volatility
+            + 0.2 * self.policy_entropy
+    }
+}
+
+fn normalized_entropy(result: &mcts::SearchResult) -> f64 {
+    let total: u32 = result.children.iter().map(|c| c.visits).sum();
+    if result.children.len() < 2 || total == 0 {
+        return 0.0;
+    }
+    let entropy: f64 = result
+        .children
+        .iter()
+        .filter(|c| c.visits > 0)
+        .map(|c| {
+            let p = c.visits as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum();
+    entropy / (result.children.len() as f64).log2()
+}
+
+/// Searches `board` to `depth` with alpha-beta and runs `playouts` MCTS
+/// iterations, then rates the position. A position with a single legal move
+/// (or none) is never difficult. Gives up once `cancel` fires.
+pub fn estimate(
+    board: &Bitboard,
+    depth: u8,
+    playouts: usize,
+    cancel: &CancelToken,
+) -> Result<Difficulty, Cancelled> {
+    if board.moves().len() < 2 {
+        return Ok(Difficulty::default());
+    }
+    let mut iterations = Vec::new();
+    let mut searcher = Searcher::new(4);
+    searcher.set_cancel(cancel.clone());
+    searcher.think_with(board, SearchLimits::depth(depth), |r| {
+        iterations.push(r.clone())
+    });
+    let mcts = if playouts > 0 {
+        let mut engine = MctsEngine::new(MctsConfig::default());
+        engine.set_cancel(cancel.clone());
+        Some(engine.search(board, playouts))
+    } else {
+        None
+    };
+    if cancel.is_cancelled() {
+        return Err(Cancelled);
+    }
+    Ok(Difficulty::from_search(&iterations, mcts.as_ref()))
+}
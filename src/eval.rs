@@ -0,0 +1,73 @@
+use crate::board::{Bitboard, Bits, GameResult, Index, Player, WIN};
+
+/// Evaluation scores are centi-fields: roughly 100 per won local field.
+pub type Score = i32;
+
+// weights of local fields (and squares) by position: corners, edges, center
+const PLACE: [Score; 9] = [3, 2, 3, 2, 4, 2, 3, 2, 3];
+
+// meta lines with one or two won fields and no opponent/tied field in them
+const META_LINE: [Score; 3] = [0, 40, 200];
+// local lines with one or two own stones and no opponent stone in them
+const LOCAL_LINE: [Score; 3] = [0, 1, 4];
+
+fn count(bits: Bits) -> usize {
+    bits.count_ones() as usize
+}
+
+/// Number of lines of a 3x3 grid without any of `blocked`, by how many of
+/// `stones` they hold (0 to 3).
+pub(crate) fn line_counts(stones: Bits, blocked: Bits) -> [u8; 4] {
+    let mut counts = [0; 4];
+    for &line in WIN.iter().filter(|&&line| line & blocked == 0) {
+        counts[count(line & stones)] += 1;
+    }
+    counts
+}
+
+/// Fields that can no longer be won by `p`: won by the opponent, tied or
+/// among `dead_fields` (as good as tied).
+pub(crate) fn closed_fields(board: &Bitboard, p: Player, dead_fields: Bits) -> Bits {
+    let own_meta = board.meta_field(p);
+    (0..9 as Index)
+        .filter(|&f| board.field_status(f).blocked() && own_meta & (1 << f) == 0)
+        .fold(dead_fields, |acc, f| acc | (1 << f))
+}
+
+// lines counted by `line_counts`, with three in a line weighing as two
+fn line_score(counts: [u8; 4], weights: [Score; 3]) -> Score {
+    let two = (counts[2] + counts[3]) as Score;
+    counts[0] as Score * weights[0] + counts[1] as Score * weights[1] + two * weights[2]
+}
+
+fn side_score(board: &Bitboard, p: Player, dead_fields: Bits) -> Score {
+    let own_meta = board.meta_field(p);
+    let closed = closed_fields(board, p, dead_fields);
+    let mut score = line_score(line_counts(own_meta, closed), META_LINE);
+    for field in 0..9 {
+        let weight = PLACE[field as usize];
+        if own_meta & (1 << field) != 0 {
+            score += 20 * weight;
+        } else if !board.field_status(field).blocked() {
+            let (own, opp) = (board.stones(p, field), board.stones(p.other(), field));
+            score += line_score(line_counts(own, opp), LOCAL_LINE) * weight;
+        }
+    }
+    score
+}
+
+// a result settled by the meta board, see `Bitboard::decided_result`; well
+// above any positional score but below the proven wins of the search
+const DECIDED: Score = 10_000;
+
+/// Static evaluation from the side to move's point of view.
+pub fn evaluate(board: &Bitboard) -> Score {
+    let p = board.side_to_move();
+    let dead = board.dead_fields();
+    match board.decided_result_with(dead) {
+        Some(GameResult::Tie) => 0,
+        Some(GameResult::Win(winner)) if winner == p => DECIDED,
+        Some(GameResult::Win(_)) => -DECIDED,
+        None => side_score(board, p, dead) - side_score(board, p.other(), dead),
+    }
+}
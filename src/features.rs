@@ -0,0 +1,245 @@
+//! Hand-crafted position features for machine learning experiments, and an
+//! exporter that turns a file of games into one CSV row per position.
+//!
+//! A game file has one game per line, as moves in the notation of
+//! [`crate::notation`]. Lines as printed by `uttt selfplay` work too: text up
+//! to the last `:` and `{...}` annotations are skipped. Empty lines and
+//! lines starting with `#` are ignored.
+
+use std::io::{self, Write};
+use std::thread;
+
+use crate::board::{Bitboard, Bits, FieldStatus, GameResult, Player};
+use crate::cancel::{CancelToken, Cancelled};
+use crate::eval::{closed_fields, evaluate, line_counts, Score};
+use crate::notation::ParsePosError;
+
+/// Column names of [`Features::write_csv`], in order.
+pub const CSV_HEADER: &str = "ply,forced,moves,tied,dead,decided,eval,\
+    won,opp_won,meta_one,opp_meta_one,meta_two,opp_meta_two,\
+    local_one,opp_local_one,local_two,opp_local_two,center,opp_center";
+
+// games handed to a thread at a time; rows are written chunk by chunk
+const CHUNK: usize = 256;
+
+/// Counts for one player, see [`Features`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SideFeatures {
+    /// Local fields won.
+    pub won: u8,
+    /// Meta lines the player can still complete holding one or two won
+    /// fields.
+    pub meta_one: u8,
+    pub meta_two: u8,
+    /// Local lines of open fields with one or two own stones and none of
+    /// the opponent's.
+    pub local_one: u8,
+    pub local_two: u8,
+    /// Whether the player won the center field.
+    pub center: bool,
+}
+
+/// Features of a position, from the side to move's point of view.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Features {
+    pub ply: u8,
+    /// Whether the side to move is sent to one field.
+    pub forced: bool,
+    /// Number of legal moves.
+    pub moves: u8,
+    pub tied: u8,
+    /// Open fields neither player can win any more.
+    pub dead: u8,
+    /// Whether the meta board already settles the result.
+    pub decided: bool,
+    /// [`evaluate`] of the position.
+    pub eval: Score,
+    pub own: SideFeatures,
+    pub opp: SideFeatures,
+}
+
+fn count(bits: Bits) -> u8 {
+    bits.count_ones() as u8
+}
+
+fn side_features(board: &Bitboard, p: Player, dead_fields: Bits) -> SideFeatures {
+    let own_meta = board.meta_field(p);
+    let [_, meta_one, meta_two, _] = line_counts(own_meta, closed_fields(board, p, dead_fields));
+    let mut side = SideFeatures {
+        won: count(own_meta),
+        meta_one,
+        meta_two,
+        center: own_meta & (1 << 4) != 0,
+        ..SideFeatures::default()
+    };
+    for field in (0..9).filter(|&f| !board.field_status(f).blocked()) {
+        let (own, opp) = (board.stones(p, field), board.stones(p.other(), field));
+        let [_, one, two, _] = line_counts(own, opp);
+        side.local_one += one;
+        side.local_two += two;
+    }
+    side
+}
+
+impl Features {
+    pub fn of(board: &Bitboard) -> Features {
+        let p = board.side_to_move();
+        let dead = board.dead_fields();
+        Features {
+            ply: board.ply() as u8,
+            forced: board.forced_field().is_some(),
+            moves: board.moves().len() as u8,
+            tied: (0..9)
+                .filter(|&f| board.field_status(f) == FieldStatus::Tied)
+                .count() as u8,
+            dead: count(dead),
+            decided: board.decided_result_with(dead).is_some(),
+            eval: evaluate(board),
+            own: side_features(board, p, dead),
+            opp: side_features(board, p.other(), dead),
+        }
+    }
+
+    /// One CSV row in the order of [`CSV_HEADER`], without a line break.
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "{},{},{},{},{},{},{}",
+            self.ply,
+            self.forced as u8,
+            self.moves,
+            self.tied,
+            self.dead,
+            self.decided as u8,
+            self.eval
+        )?;
+        for (own, opp) in [
+            (self.own.won, self.opp.won),
+            (self.own.meta_one, self.opp.meta_one),
+            (self.own.meta_two, self.opp.meta_two),
+            (self.own.local_one, self.opp.local_one),
+            (self.own.local_two, self.opp.local_two),
+            (self.own.center as u8, self.opp.center as u8),
+        ] {
+            write!(out, ",{},{}", own, opp)?;
+        }
+        Ok(())
+    }
+}
+
+/// The positions of a game file line, from `start` through every move;
+/// `Ok(None)` for a line without a game.
+pub fn parse_game_line(
+    start: Bitboard,
+    line: &str,
+) -> Result<Option<Vec<Bitboard>>, ParsePosError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let moves = line.rsplit(':').next().unwrap_or(line);
+    let mut positions = vec![start];
+    let mut board = start;
+    let mut in_annotation = false;
+    for token in moves.split_whitespace() {
+        if in_annotation || token.starts_with('{') {
+            in_annotation = !token.ends_with('}');
+            continue;
+        }
+        board.make_move(board.parse_move(token)?);
+        positions.push(board);
+    }
+    Ok(Some(positions))
+}
+
+/// What [`export_csv`] wrote and skipped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    pub games: usize,
+    pub positions: usize,
+    /// Lines (numbered from 1) that could not be parsed, with the reason.
+    pub skipped: Vec<(usize, ParsePosError)>,
+}
+
+/// Result of a game for the side to move, as the `result` column: 1, 0 or
+/// -1, empty for an unfinished game.
+fn result_label(last: &Bitboard, p: Player) -> &'static str {
+    match last.result().or_else(|| last.decided_result()) {
+        Some(GameResult::Win(winner)) if winner == p => "1",
+        Some(GameResult::Win(_)) => "-1",
+        Some(GameResult::Tie) => "0",
+        None => "",
+    }
+}
+
+// rows of the games of one chunk, numbered from `first_line`
+fn chunk_rows(start: Bitboard, lines: &[&str], first_line: usize) -> (Vec<u8>, ExportStats) {
+    let mut rows = Vec::new();
+    let mut stats = ExportStats::default();
+    for (i, line) in lines.iter().enumerate() {
+        let positions = match parse_game_line(start, line) {
+            Ok(Some(positions)) => positions,
+            Ok(None) => continue,
+            Err(err) => {
+                stats.skipped.push((first_line + i, err));
+                continue;
+            }
+        };
+        let last = positions.last().expect("the start position is included");
+        for board in &positions {
+            let label = result_label(last, board.side_to_move());
+            write!(rows, "{},", first_line + i).expect("writing to memory");
+            Features::of(board)
+                .write_csv(&mut rows)
+                .expect("writing to memory");
+            writeln!(rows, ",{}", label).expect("writing to memory");
+        }
+        stats.games += 1;
+        stats.positions += positions.len();
+    }
+    (rows, stats)
+}
+
+/// Writes a header and one row per position of every game in `games` (the
+/// contents of a game file), prefixed with the line number of the game and
+/// followed by its result for the side to move. Games are played from
+/// `start` and processed in chunks on `threads` threads; rows come out in
+/// file order. Gives up between chunks once `cancel` fires, with an
+/// [`io::ErrorKind::Interrupted`] error.
+pub fn export_csv<W: Write>(
+    games: &str,
+    start: Bitboard,
+    threads: usize,
+    cancel: &CancelToken,
+    out: &mut W,
+) -> io::Result<ExportStats> {
+    writeln!(out, "game,{},result", CSV_HEADER)?;
+    let lines: Vec<&str> = games.lines().collect();
+    let mut stats = ExportStats::default();
+    for (batch_no, batch) in lines.chunks(CHUNK * threads.max(1)).enumerate() {
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, Cancelled));
+        }
+        let first_line = batch_no * CHUNK * threads.max(1) + 1;
+        let chunks: Vec<(Vec<u8>, ExportStats)> = thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .chunks(CHUNK)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    scope.spawn(move || chunk_rows(start, chunk, first_line + i * CHUNK))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("export thread panicked"))
+                .collect()
+        });
+        for (rows, chunk_stats) in chunks {
+            out.write_all(&rows)?;
+            stats.games += chunk_stats.games;
+            stats.positions += chunk_stats.positions;
+            stats.skipped.extend(chunk_stats.skipped);
+        }
+    }
+    Ok(stats)
+}
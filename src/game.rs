@@ -0,0 +1,122 @@
+//! A board together with the moves played on it, for stepping back and forth
+//! through a game without threading [`Move`] tokens around by hand.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::board::{Bitboard, Move, MoveError, Pos};
+
+/// What an engine spent on a move, kept in the game record.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Thinking {
+    pub elapsed: Duration,
+    pub nodes: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Game {
+    board: Bitboard,
+    played: Vec<(Move, Option<Thinking>)>,
+    // undone moves, the next one to redo last
+    undone: Vec<(Pos, Option<Thinking>)>,
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game::default()
+    }
+
+    /// A game starting from `board`; moves before it cannot be undone.
+    pub fn from_board(board: Bitboard) -> Game {
+        Game {
+            board,
+            ..Game::default()
+        }
+    }
+
+    pub fn board(&self) -> &Bitboard {
+        &self.board
+    }
+
+    /// Plays a move, dropping the redo history unless it is the move that
+    /// [`Game::redo`] would have played.
+    pub fn play(&mut self, pos: Pos) -> Result<(), MoveError> {
+        self.play_with(pos, None)
+    }
+
+    /// Plays a move found by an engine, recording what it spent on it.
+    pub fn play_thought(&mut self, pos: Pos, thinking: Thinking) -> Result<(), MoveError> {
+        self.play_with(pos, Some(thinking))
+    }
+
+    fn play_with(&mut self, pos: Pos, thinking: Option<Thinking>) -> Result<(), MoveError> {
+        let mov = self.board.try_make_move(pos)?;
+        self.played.push((mov, thinking));
+        match self.undone.last() {
+            Some(&(next, _)) if next == pos => {
+                self.undone.pop();
+            }
+            _ => self.undone.clear(),
+        }
+        Ok(())
+    }
+
+    /// Takes back the last move, returning where it was played.
+    pub fn undo_last(&mut self) -> Option<Pos> {
+        let (mov, thinking) = self.played.pop()?;
+        self.board.undo_move(&mov);
+        self.undone.push((mov.pos(), thinking));
+        Some(mov.pos())
+    }
+
+    /// Replays the last undone move, along with its thinking time if any.
+    pub fn redo(&mut self) -> Option<Pos> {
+        let (pos, thinking) = *self.undone.last()?;
+        self.play_with(pos, thinking)
+            .expect("undone moves are legal");
+        Some(pos)
+    }
+
+    pub fn moves_played(&self) -> impl ExactSizeIterator<Item = Pos> + '_ {
+        self.played.iter().map(|(mov, _)| mov.pos())
+    }
+
+    /// Thinking time and nodes of every move played, `None` for moves not
+    /// played by an engine.
+    pub fn thinking(&self) -> impl ExactSizeIterator<Item = Option<Thinking>> + '_ {
+        self.played.iter().map(|&(_, thinking)| thinking)
+    }
+
+    /// The moves played, engine moves annotated like `5-5 {120ms 40312 nodes}`.
+    pub fn record(&self) -> Record<'_> {
+        Record { game: self }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.played.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}
+
+/// A [`Game`] formatted as its annotated move list, see [`Game::record`].
+pub struct Record<'a> {
+    game: &'a Game,
+}
+
+impl fmt::Display for Record<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (mov, thinking)) in self.game.played.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", mov)?;
+            if let Some(t) = thinking {
+                write!(f, " {{{}ms {} nodes}}", t.elapsed.as_millis(), t.nodes)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Ultimate boards of other sizes, for research: `N`x`N` local fields of
+//! `N`x`N` squares each, won by `N` in a row, for `N` from 2 to 5.
+//!
+//! This is a plain implementation next to [`Bitboard`] rather than
+//! `Bitboard` made generic: the 3x3 engine keeps its 9-bit fields and
+//! lookup tables, which do not carry over to other sizes. The rules both
+//! boards follow (winning lines, settling a field, closed fields) live in
+//! one place and are shared. `GenericBoard` is several times slower and has
+//! no hashing or rule variants; `GenericBoard<3>` plays exactly the same
+//! game as [`Bitboard`], which the `generic` self-check compares.
+//!
+//! [`Bitboard`]: crate::Bitboard
+
+use std::fmt;
+
+use crate::board::{FieldStatus, GameResult, MoveError, Player};
+use crate::grid::{self, MAX_LINES};
+use crate::rules::WonFields;
+
+/// Squares of a field (or fields of the meta board), bit `i` is index `i`.
+pub type Cells = u32;
+
+const MAX_CELLS: usize = 25;
+
+/// A square on a [`GenericBoard`]: a local field and a square within it,
+/// both `0..N*N` in row-major order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GenericPos {
+    field: u8,
+    square: u8,
+}
+
+impl GenericPos {
+    pub fn field(self) -> u8 {
+        self.field
+    }
+
+    pub fn square(self) -> u8 {
+        self.square
+    }
+}
+
+impl fmt::Display for GenericPos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.field + 1, self.square + 1)
+    }
+}
+
+/// A played move with what is needed to take it back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GenericMove {
+    pos: GenericPos,
+    forced: Option<u8>,
+    status: FieldStatus,
+    decided: u8,
+}
+
+impl GenericMove {
+    pub fn pos(&self) -> GenericPos {
+        self.pos
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GenericBoard<const N: usize> {
+    stones: [[Cells; MAX_CELLS]; 2],
+    status: [FieldStatus; MAX_CELLS],
+    meta: [Cells; 2],
+    forced: Option<u8>,
+    turn: Player,
+    decided: u8,
+    game_over: bool,
+}
+
+impl<const N: usize> Default for GenericBoard<N> {
+    fn default() -> Self {
+        GenericBoard::new()
+    }
+}
+
+impl<const N: usize> GenericBoard<N> {
+    /// Number of fields, and of squares per field.
+    pub const CELLS: usize = N * N;
+    const FULL: Cells = ((1u64 << (N * N)) - 1) as Cells;
+    const LINES: [Cells; MAX_LINES] = grid::win_lines(N);
+    const SIZE_OK: () = assert!(N >= 2 && N <= 5, "fields of 2x2 to 5x5 squares");
+
+    /// The empty starting position, X to move anywhere.
+    pub fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::SIZE_OK;
+        GenericBoard {
+            stones: [[0; MAX_CELLS]; 2],
+            status: [FieldStatus::None; MAX_CELLS],
+            meta: [0; 2],
+            forced: None,
+            turn: Player::X,
+            decided: 0,
+            game_over: false,
+        }
+    }
+
+    fn is_won(cells: Cells) -> bool {
+        grid::has_line(cells, &Self::LINES[..2 * N + 2])
+    }
+
+    // no rule variants: won fields are closed
+    fn is_closed(&self, field: u8) -> bool {
+        grid::is_closed(self.status[field as usize], WonFields::Closed, || {
+            self.occupied(field) == Self::FULL
+        })
+    }
+
+    pub fn pos(field: u8, square: u8) -> Option<GenericPos> {
+        let n = Self::CELLS as u8;
+        Some(GenericPos { field, square }).filter(|_| field < n && square < n)
+    }
+
+    pub fn side_to_move(&self) -> Player {
+        self.turn
+    }
+
+    /// The local field the side to move must play in, or `None` if free to play anywhere.
+    pub fn forced_field(&self) -> Option<u8> {
+        self.forced
+    }
+
+    pub fn field_status(&self, field: u8) -> FieldStatus {
+        self.status[field as usize]
+    }
+
+    /// Squares of local `field` occupied by `p`.
+    pub fn stones(&self, p: Player, field: u8) -> Cells {
+        self.stones[p.index()][field as usize]
+    }
+
+    /// Local fields won by `p`, as a meta-board bitmask.
+    pub fn meta_field(&self, p: Player) -> Cells {
+        self.meta[p.index()]
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        Player::ALL
+            .iter()
+            .copied()
+            .find(|&p| Self::is_won(self.meta[p.index()]))
+    }
+
+    /// `None` while the game is on.
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.game_over {
+            return None;
+        }
+        Some(self.winner().map_or(GameResult::Tie, GameResult::Win))
+    }
+
+    fn occupied(&self, field: u8) -> Cells {
+        self.stones[0][field as usize] | self.stones[1][field as usize]
+    }
+
+    /// Whether `pos` is legal; positions made for a larger board are
+    /// [`MoveError::OutOfRange`].
+    pub fn check_move(&self, pos: GenericPos) -> Result<(), MoveError> {
+        let n = Self::CELLS as u8;
+        if pos.field >= n || pos.square >= n {
+            Err(MoveError::OutOfRange)
+        } else if self.game_over {
+            Err(MoveError::GameOver)
+        } else if let Some(forced) = self.forced.filter(|&f| f != pos.field) {
+            Err(MoveError::WrongField { forced })
+        } else if self.is_closed(pos.field) {
+            Err(MoveError::BlockedField)
+        } else if self.occupied(pos.field) & (1 << pos.square) != 0 {
+            Err(MoveError::Occupied)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Legal moves, fields ascending and squares ascending within each.
+    pub fn moves(&self) -> impl Iterator<Item = GenericPos> + '_ {
+        let fields = match self.forced {
+            _ if self.game_over => 0..0,
+            Some(field) => field..field + 1,
+            None => 0..Self::CELLS as u8,
+        };
+        fields
+            .filter(move |&field| !self.is_closed(field))
+            .flat_map(move |field| {
+                let empty = !self.occupied(field) & Self::FULL;
+                (0..Self::CELLS as u8)
+                    .filter(move |&square| empty & (1 << square) != 0)
+                    .map(move |square| GenericPos { field, square })
+            })
+    }
+
+    /// # Panics
+    ///
+    /// If `pos` is not a legal move, see [`GenericBoard::try_make_move`].
+    pub fn make_move(&mut self, pos: GenericPos) -> GenericMove {
+        match self.try_make_move(pos) {
+            Ok(mov) => mov,
+            Err(err) => panic!("illegal move {}: {}", pos, err),
+        }
+    }
+
+    /// Plays `pos` if it is legal, returning the move to undo it with.
+    pub fn try_make_move(&mut self, pos: GenericPos) -> Result<GenericMove, MoveError> {
+        self.check_move(pos)?;
+        let (f, p) = (pos.field as usize, self.turn.index());
+        let mov = GenericMove {
+            pos,
+            forced: self.forced,
+            status: self.status[f],
+            decided: self.decided,
+        };
+        self.stones[p][f] |= 1 << pos.square;
+        let won = Self::is_won(self.stones[p][f]);
+        let full = self.occupied(pos.field) == Self::FULL;
+        self.status[f] = grid::settle(self.status[f], self.turn, won, full);
+        if self.status[f] != mov.status {
+            if self.status[f].won(self.turn) {
+                self.meta[p] |= 1 << f;
+            }
+            self.decided += 1;
+        }
+        self.game_over = Self::is_won(self.meta[p]) || self.decided as usize == Self::CELLS;
+        let next = pos.square;
+        self.forced = Some(next).filter(|&f| !self.is_closed(f));
+        self.turn = self.turn.other();
+        Ok(mov)
+    }
+
+    /// Takes back `mov`, which must be the last move made on this board.
+    ///
+    /// # Panics
+    ///
+    /// If the square of `mov` is not occupied by the player who moved last.
+    pub fn undo_move(&mut self, mov: &GenericMove) {
+        let (pos, p) = (mov.pos, self.turn.other());
+        let f = pos.field as usize;
+        assert!(
+            self.stones[p.index()][f] & (1 << pos.square) != 0,
+            "undo of a move that was not played: {}",
+            pos
+        );
+        self.stones[p.index()][f] &= !(1 << pos.square);
+        if mov.status != self.status[f] {
+            self.meta[p.index()] &= !(1 << f);
+        }
+        self.status[f] = mov.status;
+        self.decided = mov.decided;
+        self.forced = mov.forced;
+        self.game_over = false;
+        self.turn = p;
+    }
+}
+
+/// Number of positions exactly `depth` plies below `board`.
+pub fn perft<const N: usize>(board: &mut GenericBoard<N>, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves: Vec<GenericPos> = board.moves().collect();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|pos| {
+            let mov = board.make_move(pos);
+            let nodes = perft(board, depth - 1);
+            board.undo_move(&mov);
+            nodes
+        })
+        .sum()
+}
@@ -0,0 +1,62 @@
+//! Rules shared by [`Bitboard`] and [`GenericBoard`], so that the two boards
+//! cannot drift apart: the winning lines of an `n`x`n` grid, how a field is
+//! settled and when it takes no more stones.
+//!
+//! [`Bitboard`]: crate::Bitboard
+//! [`GenericBoard`]: crate::generic::GenericBoard
+
+use crate::board::{FieldStatus, Player};
+use crate::rules::WonFields;
+
+/// Most lines of a grid: 5 rows, 5 columns and 2 diagonals.
+pub(crate) const MAX_LINES: usize = 12;
+
+/// Rows, columns and both diagonals of an `n`x`n` grid, `2 * n + 2` in all,
+/// with bit `i * n + j` for row `i`, column `j`; the rest are empty.
+pub(crate) const fn win_lines(n: usize) -> [u32; MAX_LINES] {
+    let mut lines = [0; MAX_LINES];
+    let mut i = 0;
+    while i < n {
+        let mut j = 0;
+        while j < n {
+            lines[i] |= 1 << (i * n + j);
+            lines[n + i] |= 1 << (j * n + i);
+            j += 1;
+        }
+        lines[2 * n] |= 1 << (i * n + i);
+        lines[2 * n + 1] |= 1 << (i * n + n - 1 - i);
+        i += 1;
+    }
+    lines
+}
+
+/// Whether `cells` hold all of one of `lines`.
+pub(crate) fn has_line(cells: u32, lines: &[u32]) -> bool {
+    lines.iter().any(|&line| line & !cells == 0)
+}
+
+/// Status of a field after `p` played in it, given whether `p` now has a
+/// line there and whether it is full: a field counts for its first winner,
+/// and one that fills up without a winner is tied.
+pub(crate) fn settle(status: FieldStatus, p: Player, won: bool, full: bool) -> FieldStatus {
+    if status.blocked() {
+        status
+    } else if won {
+        FieldStatus::won_by(p)
+    } else if full {
+        FieldStatus::Tied
+    } else {
+        status
+    }
+}
+
+/// Whether a field takes no more stones, which also frees a player sent
+/// there to play anywhere; `full` is only asked for a won field under
+/// [`WonFields::Playable`].
+pub(crate) fn is_closed<F: FnOnce() -> bool>(
+    status: FieldStatus,
+    won_fields: WonFields,
+    full: F,
+) -> bool {
+    status.blocked() && (won_fields == WonFields::Closed || status == FieldStatus::Tied || full())
+}
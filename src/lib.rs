@@ -0,0 +1,51 @@
+//! Ultimate tic-tac-toe: a compact bitboard, move generation and perft.
+//!
+//! ```
+//! use uttt::{perft::perft, Bitboard, Pos};
+//!
+//! let mut board = Bitboard::new();
+//! assert_eq!(perft(&mut board, 2), 720);
+//! let mov = board.make_move(Pos::new(4, 4).unwrap());
+//! assert_eq!(board.forced_field(), Some(4));
+//! board.undo_move(&mov);
+//! assert_eq!(board.forced_field(), None);
+//! ```
+
+pub mod alloc;
+pub mod board;
+pub mod book;
+pub mod cancel;
+pub mod clock;
+pub mod difficulty;
+pub mod eval;
+pub mod features;
+pub mod game;
+pub mod generic;
+mod grid;
+pub mod mcts;
+pub mod movegen;
+pub mod notation;
+pub mod opening;
+pub mod ordering;
+pub mod pattern;
+pub mod perft;
+pub mod protocol;
+pub mod refutation;
+pub mod render;
+pub mod rng;
+pub mod rules;
+pub mod search;
+pub mod selfcheck;
+pub mod symmetry;
+pub mod tt;
+#[cfg(feature = "unchecked")]
+pub mod unchecked;
+pub mod verify;
+pub mod version;
+pub mod zobrist;
+
+pub use crate::board::{
+    is_dead, is_tied, is_won, Bitboard, Bits, CellChange, FieldStatus, GameResult, Index, Move,
+    Move8, MoveError, Player, Pos, ALL_FIELDS, WIN,
+};
+pub use crate::movegen::{MoveList, MoveOrder, Moves};
@@ -1,233 +1,859 @@
-use std::time::SystemTime;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::time::{Duration, Instant};
 
+use clap::{Args, Parser, Subcommand};
 use once_cell::sync::Lazy;
 
-fn main() {
-    benchmark("movegen", || {
-        println!("{}", move_gen(7));
-    });
+use uttt::board::IS_WON;
+use uttt::book::{self, Book};
+use uttt::cancel::CancelToken;
+use uttt::clock::{Clock, TimeControl};
+use uttt::difficulty::Difficulty;
+use uttt::game::{Game, Thinking};
+use uttt::generic::{self, GenericBoard};
+use uttt::notation::parse_moves_from;
+use uttt::opening::{Openings, OPENINGS};
+use uttt::ordering::MoveTable;
+use uttt::perft::{move_gen, perft_cancellable, perft_divide, perft_hashed};
+use uttt::protocol::{format_info, format_refutation};
+use uttt::refutation::RefutationTable;
+use uttt::render::Charset;
+use uttt::rng::Rng;
+use uttt::rules::{Rules, TieBreak, WonFields};
+use uttt::search::{SearchLimits, Searcher, Signature};
+use uttt::{
+    alloc, features, protocol, selfcheck, verify, version, Bitboard, GameResult, MoveOrder, Player,
+    Pos,
+};
+
+#[cfg(feature = "alloc-tracker")]
+#[global_allocator]
+static ALLOCATOR: alloc::CountingAllocator = alloc::CountingAllocator;
+
+#[derive(Parser)]
+#[command(
+    name = "uttt",
+    about = "Ultimate tic-tac-toe engine",
+    disable_version_flag = true
+)]
+struct Cli {
+    /// Print version, build and feature information
+    #[arg(short = 'V', long)]
+    version: bool,
+    /// Give up perft, analyze, selfplay, book and features after this many
+    /// milliseconds
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+    /// Name openings from this table (see `src/openings.toml` for the format)
+    /// instead of the built-in one
+    #[arg(long, global = true, value_name = "FILE")]
+    openings: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn benchmark<F>(name: &str, mut func: F)
-where
-    F: FnMut() -> (),
-{
-    println!(">>> Starting {}...", name);
-    let time = SystemTime::now();
-    func();
-    let unwrap = time.elapsed().unwrap();
-    println!(
-        "<<< Finished. Elapsed: {}s {}ms",
-        unwrap.as_secs(),
-        unwrap.subsec_millis()
-    );
+#[derive(Subcommand)]
+enum Command {
+    /// Movegen benchmark (the default command)
+    Bench {
+        /// Fail if move generation allocates, or the search does beyond its
+        /// results (requires the alloc-tracker feature)
+        #[arg(long)]
+        assert_no_alloc: bool,
+    },
+    /// Count leaf nodes at the given depth
+    Perft {
+        depth: usize,
+        /// Print the count under each root move, and their sum as the total
+        #[arg(long, conflicts_with = "hash")]
+        divide: bool,
+        /// Cache subtree counts in a table of this many MB
+        #[arg(long)]
+        hash: Option<usize>,
+        /// Count on an NxN ultimate board instead, N from 2 to 5 (see `uttt::generic`)
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u8).range(2..=5),
+            conflicts_with_all = ["divide", "hash", "moves"]
+        )]
+        size: Option<u8>,
+        #[command(flatten)]
+        position: PositionArg,
+    },
+    /// Search a position and print the principal variation
+    Analyze {
+        #[command(flatten)]
+        position: PositionArg,
+        #[command(flatten)]
+        limits: LimitArgs,
+        /// Also refute every other move with a search this many plies deep
+        #[arg(long, value_name = "DEPTH")]
+        refute: Option<u8>,
+    },
+    /// Play against the engine on stdin/stdout; enter `undo` or `redo` to step a turn
+    Play {
+        #[command(flatten)]
+        position: PositionArg,
+        #[command(flatten)]
+        limits: LimitArgs,
+        /// Side played by the human, X or O
+        #[arg(long, default_value = "X", value_parser = parse_player)]
+        human: Player,
+        /// Draw the board with box-drawing characters
+        #[arg(long)]
+        unicode: bool,
+        #[command(flatten)]
+        book: BookArg,
+    },
+    /// Let the engine play against itself
+    Selfplay {
+        #[arg(long, default_value_t = 10)]
+        games: usize,
+        /// Random plies played before the engine takes over, for variety
+        #[arg(long, default_value_t = 2)]
+        random_plies: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// End a game as soon as the meta board settles its result
+        #[arg(long)]
+        adjudicate: bool,
+        #[command(flatten)]
+        position: PositionArg,
+        #[command(flatten)]
+        limits: LimitArgs,
+        #[command(flatten)]
+        book: BookArg,
+    },
+    /// Build an opening book from searches of early positions, and from played games
+    Book {
+        /// Book file to write
+        out: String,
+        /// Search every position up to this many moves after the start
+        #[arg(long, default_value_t = 2)]
+        plies: usize,
+        /// Search depth per position
+        #[arg(long, default_value_t = 10)]
+        depth: u8,
+        /// Also add the early moves of the winners of these games, one per line
+        #[arg(long)]
+        games: Option<String>,
+        /// Moves of each game to add
+        #[arg(long, default_value_t = 12)]
+        game_plies: usize,
+        /// Transposition table size in MB
+        #[arg(long, default_value_t = protocol::DEFAULT_HASH_MB)]
+        hash: usize,
+        #[command(flatten)]
+        position: PositionArg,
+    },
+    /// Write features of every position in a game file as CSV (see `uttt::features`)
+    Features {
+        /// One game per line, e.g. the output of selfplay
+        games: String,
+        /// Output file; stdout if not given
+        #[arg(long)]
+        out: Option<String>,
+        #[arg(long, default_value_t = default_threads())]
+        threads: usize,
+        #[command(flatten)]
+        position: PositionArg,
+    },
+    /// Cross-check the engine against reference implementations
+    #[command(subcommand)]
+    Verify(Verify),
+    /// Quick sanity battery
+    Selfcheck,
+    /// UCI-like protocol on stdin/stdout
+    Uci,
 }
 
-type Index = u8;
-type Bits = u16;
+#[derive(Subcommand)]
+enum Verify {
+    /// Compare move generation against a naive rules implementation
+    Movegen {
+        #[arg(long, default_value_t = 1000)]
+        games: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Feed random input to every parser and report panics
+    Parsers {
+        #[arg(long, default_value_t = 100_000)]
+        cases: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
 
-pub const WIN: [Bits; 8] = [0o421, 0o124, 0o700, 0o070, 0o007, 0o111, 0o222, 0o444];
-pub const ALL_FIELDS: Bits = 0o777;
+#[derive(Args)]
+struct PositionArg {
+    /// Moves from the start position, e.g. "5-1 1-5 5-9"
+    #[arg(long = "position", default_value = "")]
+    moves: String,
+    /// Winner of a game whose meta board fills without a line: none or fields (more fields won)
+    #[arg(long, default_value_t = TieBreak::None)]
+    tie_break: TieBreak,
+    /// Whether won fields take more stones: closed or playable (until full)
+    #[arg(long, default_value_t = WonFields::Closed)]
+    won_fields: WonFields,
+}
 
-pub static IS_WON: Lazy<Vec<bool>> = Lazy::new(|| {
-    (0..1024)
-        .map(|field| WIN.iter().any(|&w| field & w == w))
-        .collect()
-});
+impl PositionArg {
+    fn board(&self) -> Bitboard {
+        let start = Bitboard::with_rules(Rules {
+            tie_break: self.tie_break,
+            won_fields: self.won_fields,
+        });
+        let moves = self
+            .moves
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|m| !m.is_empty());
+        parse_moves_from(start, moves).unwrap_or_else(|err| {
+            eprintln!("error: invalid --position: {}", err);
+            std::process::exit(2);
+        })
+    }
 
-#[repr(packed)]
-#[derive(Copy, Clone, Default)]
-pub struct Pos {
-    pub field: Index,
-    pub square: Bits,
+    /// The moves of `--position`, which [`PositionArg::board`] has checked.
+    fn moves(&self) -> Vec<Pos> {
+        self.moves
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|m| m.parse().ok())
+            .collect()
+    }
 }
 
-#[repr(packed)]
-#[derive(Copy, Clone)]
-pub struct Move {
-    pos: Pos,
-    all_valid: bool,
-    field_status: FieldStatus,
-    meta_field: Bits,
-    n_blocked: u8,
+#[derive(Args)]
+struct LimitArgs {
+    /// Maximum search depth in plies
+    #[arg(long)]
+    depth: Option<u8>,
+    /// Maximum number of nodes
+    #[arg(long)]
+    nodes: Option<u64>,
+    /// Time per move in milliseconds; 1000 if no limit is given
+    #[arg(long)]
+    movetime: Option<u64>,
+    /// Transposition table size in MB
+    #[arg(long, default_value_t = protocol::DEFAULT_HASH_MB)]
+    hash: usize,
+    /// Move ordering weights: nine for the fields, then nine for the squares,
+    /// e.g. "2,1,2,1,3,1,2,1,2/2,1,2,1,3,1,2,1,2" (the default)
+    #[arg(long, value_name = "FIELDS/SQUARES")]
+    weights: Option<MoveTable>,
+    /// Order moves of equal weight are tried in: natural or center-first
+    #[arg(long, default_value_t = MoveOrder::Natural)]
+    order: MoveOrder,
+    /// Time per side in milliseconds: moves take a share of what is left
+    /// unless --movetime is given, and in play and selfplay a player whose
+    /// clock runs out loses
+    #[arg(long = "clock", value_name = "MS")]
+    base: Option<u64>,
+    /// Milliseconds added to a player's clock after every move
+    #[arg(long, default_value_t = 0, requires = "base")]
+    increment: u64,
+    /// Milliseconds of every move not charged to the clock (simple delay)
+    #[arg(long, default_value_t = 0, requires = "base")]
+    delay: u64,
 }
 
-#[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub enum FieldStatus {
-    Won0 = 0,
-    Won1 = 1,
-    Tied,
-    None,
-}
+impl LimitArgs {
+    fn searcher(&self) -> Searcher {
+        let mut searcher = Searcher::new(self.hash);
+        searcher.set_move_order(self.order);
+        if let Some(table) = &self.weights {
+            searcher.set_move_table(table.clone());
+        }
+        searcher
+    }
 
-impl FieldStatus {
-    pub fn blocked(self) -> bool {
-        self != FieldStatus::None
+    fn limits(&self) -> SearchLimits {
+        let clock = self.clock();
+        self.limits_on(clock.as_ref(), Player::X)
     }
 
-    pub fn won(self, p: usize) -> bool {
-        (self as u8) as usize == p
+    /// Limits for a move of `p` with `clock` running.
+    fn limits_on(&self, clock: Option<&Clock>, p: Player) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            movetime: self.movetime.map(Duration::from_millis),
+        };
+        if let (Some(clock), None) = (clock, limits.movetime) {
+            limits.movetime = Some(clock.allot(p));
+        }
+        if limits == SearchLimits::default() {
+            limits.movetime = Some(Duration::from_millis(1000));
+        }
+        limits
     }
-}
 
-impl Default for FieldStatus {
-    fn default() -> Self {
-        FieldStatus::None
+    fn clock(&self) -> Option<Clock> {
+        let control = TimeControl::new(
+            Duration::from_millis(self.base?),
+            Duration::from_millis(self.increment),
+        );
+        Some(Clock::new(
+            control.with_delay(Duration::from_millis(self.delay)),
+        ))
     }
 }
 
-#[derive(Copy, Clone, Debug, Default)]
-pub struct Bitboard {
-    valid_field: Option<Index>,
-    board: [[Bits; 9]; 2],
-    turn: usize,
-    field_status: [FieldStatus; 9],
-    meta_field: [Bits; 2],
-    game_over: bool,
-    n_blocked: u8,
+// presses `clock` after a move, `Some(p)` if `p` ran out of time on it
+fn press(clock: &mut Option<Clock>) -> Option<Player> {
+    let clock = clock.as_mut()?;
+    clock.press();
+    clock.flagged()
 }
 
-impl Bitboard {
-    fn get(&self, p: usize, field: Index) -> Bits {
-        let f = field as usize;
-        unsafe { *self.board.get_unchecked(p).get_unchecked(f) }
-    }
+fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
 
-    fn get_mut(&mut self, p: usize, field: Index) -> &mut Bits {
-        let f = field as usize;
-        unsafe { self.board.get_unchecked_mut(p).get_unchecked_mut(f) }
+fn export_features(
+    path: &str,
+    out: Option<&str>,
+    threads: usize,
+    start: Bitboard,
+    cancel: &CancelToken,
+) {
+    let games = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("error: {}: {}", path, err);
+        std::process::exit(1);
+    });
+    let result = match out {
+        Some(out) => File::create(out).and_then(|file| {
+            features::export_csv(&games, start, threads, cancel, &mut BufWriter::new(file))
+        }),
+        None => features::export_csv(&games, start, threads, cancel, &mut io::stdout().lock()),
+    };
+    match result {
+        Ok(stats) => {
+            for (line, err) in &stats.skipped {
+                eprintln!("warning: {}:{}: {}", path, line, err);
+            }
+            eprintln!(
+                "exported {} positions from {} games, skipped {} lines",
+                stats.positions,
+                stats.games,
+                stats.skipped.len()
+            );
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
     }
+}
 
-    fn get_fields(&self, field: Index) -> (Bits, Bits) {
-        (self.get(0, field), self.get(1, field))
-    }
+#[derive(Args)]
+struct BookArg {
+    /// Play from this opening book (see `uttt book`) while it has a move
+    #[arg(long = "book")]
+    path: Option<String>,
+}
 
-    fn get_field_status(&mut self, field: Index) -> FieldStatus {
-        unsafe { *self.field_status.get_unchecked(field as usize) }
+impl BookArg {
+    fn load(&self) -> Option<Book> {
+        let path = self.path.as_ref()?;
+        Some(Book::load(path).unwrap_or_else(|err| {
+            eprintln!("error: --book {}: {}", path, err);
+            std::process::exit(2);
+        }))
     }
+}
 
-    fn get_meta_field(&mut self, p: usize) -> Bits {
-        unsafe { *self.meta_field.get_unchecked(p) }
+#[allow(clippy::too_many_arguments)]
+fn build_book(
+    out: &str,
+    plies: usize,
+    depth: u8,
+    games: Option<&str>,
+    game_plies: usize,
+    hash: usize,
+    start: Bitboard,
+    cancel: &CancelToken,
+) {
+    let mut book = Book::new();
+    let time = Instant::now();
+    let mut searcher = Searcher::new(hash);
+    searcher.set_cancel(cancel.clone());
+    match book::add_searches(&mut book, &mut searcher, &start, plies, depth, cancel) {
+        Ok(searched) => println!("searched {} positions in {:?}", searched, time.elapsed()),
+        Err(err) => {
+            eprintln!("error: book searches: {} after {:?}", err, time.elapsed());
+            std::process::exit(1);
+        }
     }
-
-    fn set_field_status(&mut self, field: Index, status: FieldStatus) {
-        unsafe { *self.field_status.get_unchecked_mut(field as usize) = status };
+    if let Some(path) = games {
+        let text = fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("error: {}: {}", path, err);
+            std::process::exit(1);
+        });
+        let mut added = 0;
+        for (i, line) in text.lines().enumerate() {
+            match features::parse_game_line(start, line) {
+                Ok(Some(positions)) => {
+                    let moves: Vec<Pos> = positions[1..]
+                        .iter()
+                        .map(|board| board.last_move().expect("a move was played"))
+                        .collect();
+                    book.add_game(start, &moves, game_plies);
+                    added += 1;
+                }
+                Ok(None) => {}
+                Err(err) => eprintln!("warning: {}:{}: {}", path, i + 1, err),
+            }
+        }
+        println!("added {} games", added);
     }
+    if let Err(err) = book.save(out) {
+        eprintln!("error: {}: {}", out, err);
+        std::process::exit(1);
+    }
+    println!("wrote {} moves to {}", book.len(), out);
+}
 
-    fn set_meta_field(&mut self, p: usize, meta_field: Bits) {
-        unsafe { *self.meta_field.get_unchecked_mut(p) = meta_field };
+fn parse_player(s: &str) -> Result<Player, String> {
+    match s {
+        "X" | "x" => Ok(Player::X),
+        "O" | "o" => Ok(Player::O),
+        _ => Err(format!("expected X or O, got {:?}", s)),
     }
+}
 
-    pub fn make_move(&mut self, pos: Pos) {
-        let square = self.get_mut(self.turn, pos.field);
-        *square |= pos.square;
-        let square = *square;
-        if is_won(square) {
-            self.set_field_status(pos.field, unsafe { std::mem::transmute(self.turn as u8) });
-            self.valid_field = None;
-            let meta = self.get_meta_field(self.turn) | (1 << pos.field as Bits);
-            self.set_meta_field(self.turn, meta);
-            self.n_blocked += 1;
-            if self.n_blocked == 9 || is_won(meta) {
-                self.game_over = true;
+fn main() {
+    let cli = Cli::parse();
+    if cli.version {
+        println!("{}", version::report());
+        return;
+    }
+    let cancel = cli.timeout.map_or_else(CancelToken::new, |ms| {
+        CancelToken::with_timeout(Duration::from_millis(ms))
+    });
+    let openings = match &cli.openings {
+        Some(path) => Openings::load(path).unwrap_or_else(|err| {
+            eprintln!("error: --openings {}: {}", path, err);
+            std::process::exit(2);
+        }),
+        None => OPENINGS.clone(),
+    };
+    match cli.command.unwrap_or(Command::Bench {
+        assert_no_alloc: false,
+    }) {
+        Command::Bench { assert_no_alloc } => bench(assert_no_alloc),
+        Command::Perft {
+            depth,
+            size: Some(size),
+            ..
+        } => {
+            let time = Instant::now();
+            let nodes = match size {
+                2 => generic::perft(&mut GenericBoard::<2>::new(), depth),
+                3 => generic::perft(&mut GenericBoard::<3>::new(), depth),
+                4 => generic::perft(&mut GenericBoard::<4>::new(), depth),
+                _ => generic::perft(&mut GenericBoard::<5>::new(), depth),
+            };
+            println!(
+                "perft {} ({}x{}): {} ({:?})",
+                depth,
+                size,
+                size,
+                nodes,
+                time.elapsed()
+            );
+        }
+        Command::Perft {
+            depth,
+            divide,
+            hash,
+            size: None,
+            position,
+        } => {
+            let mut board = position.board();
+            let time = Instant::now();
+            let nodes = if divide {
+                perft_divide(&mut board, depth, &cancel).map(|divide| {
+                    for (pos, nodes) in &divide {
+                        println!("{}: {}", pos, nodes);
+                    }
+                    // depth 0 has no root moves but one position
+                    match depth {
+                        0 => 1,
+                        _ => divide.iter().map(|&(_, nodes)| nodes).sum(),
+                    }
+                })
+            } else {
+                match hash {
+                    Some(mb) => perft_hashed(&mut board, depth, mb, &cancel),
+                    None => perft_cancellable(&mut board, depth, &cancel),
+                }
+            };
+            match nodes {
+                Ok(nodes) => println!("perft {}: {} ({:?})", depth, nodes, time.elapsed()),
+                Err(err) => {
+                    eprintln!("error: perft {}: {} after {:?}", depth, err, time.elapsed());
+                    std::process::exit(1);
+                }
             }
-        } else {
-            let other = self.get(1 - self.turn, pos.field);
-            if is_tied(square | other) {
-                self.set_field_status(pos.field, FieldStatus::Tied);
-                self.valid_field = None;
-                self.n_blocked += 1;
-                if self.n_blocked == 9 {
-                    self.game_over = true;
+        }
+        Command::Analyze {
+            position,
+            limits,
+            refute,
+        } => {
+            let board = position.board();
+            let opening = openings.classify(&position.moves());
+            println!("opening {}", opening.map_or("unnamed", |o| &o.name));
+            let mut searcher = limits.searcher();
+            searcher.set_cancel(cancel);
+            let mut iterations = Vec::new();
+            let result = searcher.think_with(&board, limits.limits(), |r| {
+                println!("{}", format_info(r));
+                iterations.push(r.clone());
+            });
+            let difficulty = Difficulty::from_search(&iterations, None);
+            println!("difficulty {:.2}", difficulty.combined());
+            if let Some(depth) = refute {
+                let table = RefutationTable::build(&mut searcher, &board, result.best, depth);
+                for refutation in table.iter() {
+                    println!("{}", format_refutation(refutation));
                 }
-            } else {
-                self.valid_field = Some(pos.square.trailing_zeros() as _);
+            }
+            match result.best {
+                Some(pos) => println!("bestmove {}", pos),
+                None => println!("bestmove none"),
             }
         }
-        self.turn = 1 - self.turn;
-    }
-
-    pub fn get_all_moves<F: FnMut(&mut Bitboard, Move)>(&mut self, mut f: F) {
-        let all_valid = self.valid_field.is_none();
-        let available_fields = match self.valid_field {
-            Some(field) => field..field + 1,
-            _ => 0..9,
-        };
-        let meta_field = self.get_meta_field(self.turn);
-        let n_blocked = self.n_blocked;
-        for field in available_fields {
-            let field_status = self.get_field_status(field);
-            if field_status.blocked() {
-                continue;
+        Command::Play {
+            position,
+            limits,
+            human,
+            unicode,
+            book,
+        } => {
+            let charset = if unicode {
+                Charset::Unicode
+            } else {
+                Charset::Ascii
+            };
+            play(
+                position.board(),
+                &limits,
+                book.load().as_ref(),
+                human,
+                charset,
+            )
+        }
+        Command::Selfplay {
+            games,
+            random_plies,
+            seed,
+            adjudicate,
+            position,
+            limits,
+            book,
+        } => selfplay(
+            position.board(),
+            &limits,
+            book.load().as_ref(),
+            games,
+            random_plies,
+            seed,
+            adjudicate,
+            &openings,
+            cancel,
+        ),
+        Command::Book {
+            out,
+            plies,
+            depth,
+            games,
+            game_plies,
+            hash,
+            position,
+        } => build_book(
+            &out,
+            plies,
+            depth,
+            games.as_deref(),
+            game_plies,
+            hash,
+            position.board(),
+            &cancel,
+        ),
+        Command::Features {
+            games,
+            out,
+            threads,
+            position,
+        } => export_features(&games, out.as_deref(), threads, position.board(), &cancel),
+        Command::Verify(Verify::Movegen { games, seed }) => {
+            match verify::verify_movegen(games, seed) {
+                Ok(plies) => println!(
+                    "verified {} games ({} plies), seed {}: ok",
+                    games, plies, seed
+                ),
+                Err(divergence) => {
+                    println!("{}", divergence);
+                    std::process::exit(1);
+                }
             }
-            let (white, black) = self.get_fields(field);
-            let any = white | black;
-            for square in 0..9 {
-                let square = 1 << square;
-                let taken = any & square != 0;
-                if taken {
-                    continue;
+        }
+        Command::Verify(Verify::Parsers { cases, seed }) => {
+            match verify::verify_parsers(cases, seed) {
+                Ok(parsers) => println!(
+                    "verified {} parsers on {} inputs, seed {}: ok",
+                    parsers, cases, seed
+                ),
+                Err(panic) => {
+                    println!("{}", panic);
+                    std::process::exit(1);
                 }
-                let pos = Pos { field, square };
-                f(
-                    self,
-                    Move {
-                        pos,
-                        all_valid,
-                        field_status,
-                        meta_field,
-                        n_blocked,
-                    },
-                );
+            }
+        }
+        Command::Selfcheck => {
+            println!("{}", version::id());
+            let checks = selfcheck::selfcheck();
+            for check in &checks {
+                println!("{}", check);
+            }
+            let failed = checks.iter().filter(|c| !c.passed).count();
+            if failed != 0 {
+                println!("{} of {} checks failed", failed, checks.len());
+                std::process::exit(1);
+            }
+            println!("all {} checks passed", checks.len());
+        }
+        Command::Uci => {
+            if let Err(err) = protocol::run() {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
             }
         }
     }
+}
 
-    pub fn undo_move(&mut self, mov: &Move) {
-        let pos = mov.pos;
-        self.turn = 1 - self.turn;
-        *self.get_mut(self.turn, pos.field) &= !pos.square;
-        self.valid_field = if mov.all_valid { None } else { Some(pos.field) };
-        self.set_field_status(pos.field, mov.field_status);
-        self.set_meta_field(self.turn, mov.meta_field);
-        self.n_blocked = mov.n_blocked;
-        self.game_over = false;
-    }
+const BENCH_DEPTH: u8 = 10;
+const BENCH_HASH_MB: usize = 4;
 
-    pub fn game_over(&self) -> bool {
-        self.game_over
+fn bench(assert_no_alloc: bool) {
+    if assert_no_alloc && !alloc::enabled() {
+        eprintln!("error: --assert-no-alloc requires the alloc-tracker feature");
+        std::process::exit(2);
     }
+    println!("{}", version::id());
+    // lazily initialized tables must not count as search allocations
+    Lazy::force(&IS_WON);
+    let mut nodes = 0;
+    let mut allocations = None;
+    benchmark("movegen", || {
+        allocations = alloc::count(|| nodes = move_gen(7));
+    });
+    println!("{}", nodes);
+    if let Some(allocations) = allocations {
+        println!("allocations: {}", allocations);
+        if assert_no_alloc && allocations != 0 {
+            eprintln!("error: move generation allocated on the heap");
+            std::process::exit(1);
+        }
+    }
+    // fixed depth and table size, so that TT layouts compare like for like
+    let mut searcher = Searcher::new(BENCH_HASH_MB);
+    let mut result = None;
+    let mut signature = Signature::default();
+    benchmark("search", || {
+        let limits = SearchLimits::depth(BENCH_DEPTH);
+        let board = Bitboard::new();
+        allocations = alloc::count(|| {
+            result = Some(searcher.think_with(&board, limits, |r| signature.update(r)))
+        });
+    });
+    let result = result.expect("benchmark ran");
+    if let Some(allocations) = allocations {
+        println!("search allocations: {}", allocations);
+        // every iteration returns a principal variation, nothing else may allocate
+        if assert_no_alloc && allocations > result.depth as usize {
+            eprintln!("error: search allocated on the heap beyond its results");
+            std::process::exit(1);
+        }
+    }
+    println!(
+        "depth {}, {} nodes, {} knps, tt {} MB / {} entries, hit rate {:.1}%",
+        result.depth,
+        result.nodes,
+        result.nodes / result.elapsed.as_millis().max(1) as u64,
+        searcher.tt().size_mb(),
+        searcher.tt().capacity(),
+        100.0 * result.tt_hits as f64 / result.tt_probes.max(1) as f64
+    );
+    println!("search signature: {:016x}", signature.value());
 }
 
-pub fn is_tied(field: Bits) -> bool {
-    field == ALL_FIELDS
-}
-
-pub fn is_won(field: Bits) -> bool {
-    unsafe { *IS_WON.get_unchecked(field as usize) }
+fn play(board: Bitboard, limits: &LimitArgs, book: Option<&Book>, human: Player, charset: Charset) {
+    let mut game = Game::from_board(board);
+    let mut searcher = limits.searcher();
+    let mut lines = io::stdin().lock().lines();
+    let mut clock = limits.clock();
+    if let Some(clock) = clock.as_mut() {
+        clock.start(board.side_to_move());
+    }
+    let mut flagged = None;
+    while !game.board().game_over() && flagged.is_none() {
+        let board = *game.board();
+        if board.side_to_move() != human {
+            if let Some(pos) = book.and_then(|book| book.probe(&board)) {
+                println!("engine plays {} (book)", pos);
+                game.play(pos).expect("book moves are legal");
+                flagged = press(&mut clock);
+                continue;
+            }
+            let limits = limits.limits_on(clock.as_ref(), board.side_to_move());
+            let result = searcher.think(&board, limits);
+            let pos = result.best.expect("no move in a running game");
+            println!("engine plays {} ({})", pos, format_info(&result));
+            let thinking = Thinking {
+                elapsed: result.elapsed,
+                nodes: result.nodes,
+            };
+            game.play_thought(pos, thinking)
+                .expect("engine moves are legal");
+            flagged = press(&mut clock);
+            continue;
+        }
+        println!("{}", board.render(charset));
+        if let Some(clock) = &clock {
+            let [x, o] = Player::ALL.map(|p| clock.remaining(p).as_secs_f64());
+            print!("X {:.1}s, O {:.1}s ", x, o);
+        }
+        print!("> ");
+        io::stdout().flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return,
+        };
+        // step back or forth by full turns so that it is the human to move again
+        match line.trim() {
+            "undo" if game.can_undo() => {
+                while game.undo_last().is_some() && game.board().side_to_move() != human {}
+            }
+            "redo" if game.can_redo() => {
+                while game.redo().is_some() && game.board().side_to_move() != human {}
+            }
+            "undo" | "redo" => println!("nothing to {}", line.trim()),
+            _ => match board.parse_move(&line) {
+                Ok(pos) => {
+                    game.play(pos).expect("parsed moves are legal");
+                    flagged = press(&mut clock);
+                }
+                Err(err) => println!("{}", err),
+            },
+        }
+    }
+    println!("{}", game.board().render(charset));
+    if let Some(p) = flagged {
+        println!("{} loses on time", p);
+    }
+    println!("{}", game.record());
 }
 
-pub fn move_gen_impl(board: &mut Bitboard, depth: usize) -> usize {
-    if board.game_over() {
-        0
-    } else {
-        let mut sum = 0;
-        if depth != 0 {
-            board.get_all_moves(|b, mov| {
-                b.make_move(mov.pos);
-                sum += 1 + move_gen_impl(b, depth - 1);
-                b.undo_move(&mov);
-            });
-        } else {
-            board.get_all_moves(|_, _| {
-                sum += 1;
-            })
+#[allow(clippy::too_many_arguments)]
+fn selfplay(
+    start: Bitboard,
+    limits: &LimitArgs,
+    book: Option<&Book>,
+    games: usize,
+    random_plies: usize,
+    seed: u64,
+    adjudicate: bool,
+    openings: &Openings,
+    cancel: CancelToken,
+) {
+    let mut searcher = limits.searcher();
+    searcher.set_cancel(cancel.clone());
+    let (mut wins, mut ties) = ([0; 2], 0);
+    'games: for game_no in 0..games {
+        let mut rng = Rng::for_worker(seed, game_no);
+        searcher.new_game(false);
+        let mut game = Game::from_board(start);
+        let mut clock = limits.clock();
+        if let Some(clock) = clock.as_mut() {
+            clock.start(start.side_to_move());
         }
-        sum
+        let mut flagged = None;
+        while !game.board().game_over() && flagged.is_none() {
+            if cancel.is_cancelled() {
+                println!("game {}: cancelled", game_no + 1);
+                break 'games;
+            }
+            if adjudicate && game.board().decided_result().is_some() {
+                break;
+            }
+            let board = *game.board();
+            if game.moves_played().len() < random_plies {
+                let pos = *rng
+                    .choose(&board.moves().collect::<Vec<_>>())
+                    .expect("no move in a running game");
+                game.play(pos).expect("generated moves are legal");
+            } else if let Some(pos) = book.and_then(|book| book.probe(&board)) {
+                game.play(pos).expect("book moves are legal");
+            } else {
+                let limits = limits.limits_on(clock.as_ref(), board.side_to_move());
+                let result = searcher.think(&board, limits);
+                let pos = result.best.expect("no move in a running game");
+                let thinking = Thinking {
+                    elapsed: result.elapsed,
+                    nodes: result.nodes,
+                };
+                game.play_thought(pos, thinking)
+                    .expect("engine moves are legal");
+            }
+            flagged = press(&mut clock);
+        }
+        let result = match flagged {
+            Some(p) => GameResult::Win(p.other()),
+            None => game
+                .board()
+                .decided_result()
+                .expect("the game is over or decided"),
+        };
+        match result {
+            GameResult::Win(p) => wins[p.index()] += 1,
+            GameResult::Tie => ties += 1,
+        }
+        let moves: Vec<Pos> = game.moves_played().collect();
+        let opening = openings.classify(&moves).map_or("unnamed", |o| &o.name);
+        let adjudicated = if flagged.is_some() {
+            ", on time"
+        } else if game.board().game_over() {
+            ""
+        } else {
+            ", adjudicated"
+        };
+        println!(
+            "game {}: {}{} ({}): {}",
+            game_no + 1,
+            result,
+            adjudicated,
+            opening,
+            game.record()
+        );
     }
+    println!("X wins {}, O wins {}, ties {}", wins[0], wins[1], ties);
 }
 
-pub fn move_gen(depth: usize) -> usize {
-    move_gen_impl(&mut Default::default(), depth)
+fn benchmark<F>(name: &str, mut func: F)
+where
+    F: FnMut(),
+{
+    println!(">>> Starting {}...", name);
+    let time = Instant::now();
+    func();
+    let elapsed = time.elapsed();
+    println!(
+        "<<< Finished. Elapsed: {}s {}ms",
+        elapsed.as_secs(),
+        elapsed.subsec_millis()
+    );
 }
@@ -0,0 +1,262 @@
+use crate::board::{Bitboard, Index, Move, Player, Pos};
+use crate::cancel::CancelToken;
+use crate::rng::Rng;
+
+/// Settings for [`MctsEngine`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MctsConfig {
+    /// UCT exploration constant `c` in `q + c * sqrt(ln N / n)`.
+    pub exploration: f64,
+    /// Seed for the playout policy; equal seeds give identical searches.
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            exploration: std::f64::consts::SQRT_2,
+            seed: 0,
+        }
+    }
+}
+
+/// Visit statistics of a root move after a search.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChildStats {
+    pub pos: Pos,
+    pub visits: u32,
+    /// Average playout score for the side to move at the root, in `[0, 1]`.
+    pub value: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// Most visited root move, `None` if the game is already over.
+    pub best: Option<Pos>,
+    pub children: Vec<ChildStats>,
+    pub iterations: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Node {
+    mov: Move,
+    // player who made `mov`, i.e. the side to move at the parent
+    mover: Player,
+    first_child: u32,
+    n_children: u8,
+    expanded: bool,
+    visits: u32,
+    // 2 per win, 1 per tie, from `mover`'s point of view
+    half_points: u32,
+}
+
+impl Node {
+    fn new(mov: Move, mover: Player) -> Node {
+        Node {
+            mov,
+            mover,
+            first_child: 0,
+            n_children: 0,
+            expanded: false,
+            visits: 0,
+            half_points: 0,
+        }
+    }
+
+    fn value(&self) -> f64 {
+        self.half_points as f64 / (2 * self.visits.max(1)) as f64
+    }
+
+    fn children(&self) -> std::ops::Range<usize> {
+        let first = self.first_child as usize;
+        first..first + self.n_children as usize
+    }
+}
+
+/// Monte Carlo tree search with UCT selection and uniformly random playouts.
+pub struct MctsEngine {
+    config: MctsConfig,
+    rng: Rng,
+    nodes: Vec<Node>,
+    cancel: CancelToken,
+}
+
+impl MctsEngine {
+    pub fn new(config: MctsConfig) -> MctsEngine {
+        MctsEngine {
+            config,
+            rng: Rng::new(config.seed),
+            nodes: Vec::new(),
+            cancel: CancelToken::default(),
+        }
+    }
+
+    pub fn config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    /// Forgets the previous game: the tree is dropped and the random stream
+    /// restarts from the configured seed, so every game replays identically.
+    pub fn new_game(&mut self) {
+        self.rng = Rng::new(self.config.seed);
+        self.nodes.clear();
+    }
+
+    /// Token checked before every iteration; once it fires, searches stop
+    /// early and report the iterations completed so far.
+    pub fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    /// Runs `iterations` select/expand/playout/backpropagate cycles from `board`.
+    pub fn search(&mut self, board: &Bitboard, iterations: usize) -> SearchResult {
+        let mut board = *board;
+        self.nodes.clear();
+        if board.game_over() {
+            return SearchResult {
+                best: None,
+                children: Vec::new(),
+                iterations: 0,
+            };
+        }
+        // the root's move is never played or undone
+        self.nodes
+            .push(Node::new(Move::NULL, board.side_to_move().other()));
+        let mut path = Vec::new();
+        let mut done = 0;
+        while done < iterations && !self.cancel.is_cancelled() {
+            self.iterate(&mut board, &mut path);
+            done += 1;
+        }
+        self.result(done)
+    }
+
+    fn iterate(&mut self, board: &mut Bitboard, path: &mut Vec<usize>) {
+        path.clear();
+        path.push(0);
+        let mut node = 0;
+        // the position before every move of the descent
+        #[cfg(feature = "check-undo")]
+        let mut snapshots = vec![*board];
+        // selection
+        while self.nodes[node].expanded && self.nodes[node].n_children != 0 {
+            node = self.select(node);
+            // SAFETY: children are generated for the position they descend from
+            unsafe { board.make_move_unchecked(self.nodes[node].mov.pos()) };
+            #[cfg(feature = "check-undo")]
+            snapshots.push(*board);
+            path.push(node);
+        }
+        // expansion
+        if !board.game_over() {
+            self.expand(node, board);
+            let children = self.nodes[node].children();
+            node = children.start + self.rng.below(children.len());
+            unsafe { board.make_move_unchecked(self.nodes[node].mov.pos()) };
+            #[cfg(feature = "check-undo")]
+            snapshots.push(*board);
+            path.push(node);
+        }
+        let winner = playout(*board, &mut self.rng);
+        // backpropagation, undoing the descent on the way up
+        for &index in path.iter().rev() {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.half_points += match winner {
+                Some(p) if p == node.mover => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+            if index != 0 {
+                // SAFETY: the path is unwound in reverse order of the descent
+                unsafe { board.undo_move_unchecked(&node.mov) };
+                #[cfg(feature = "check-undo")]
+                {
+                    snapshots.pop();
+                    let before = snapshots.last().expect("one snapshot per move");
+                    before.assert_restored(board, node.mov.pos());
+                }
+            }
+        }
+    }
+
+    fn select(&self, parent: usize) -> usize {
+        let parent = &self.nodes[parent];
+        let log_n = (parent.visits.max(1) as f64).ln();
+        let c = self.config.exploration;
+        let mut best = (f64::NEG_INFINITY, parent.first_child as usize);
+        for index in parent.children() {
+            let child = &self.nodes[index];
+            if child.visits == 0 {
+                return index;
+            }
+            let uct = child.value() + c * (log_n / child.visits as f64).sqrt();
+            if uct > best.0 {
+                best = (uct, index);
+            }
+        }
+        best.1
+    }
+
+    fn expand(&mut self, node: usize, board: &mut Bitboard) {
+        let first = self.nodes.len();
+        let mover = board.side_to_move();
+        let nodes = &mut self.nodes;
+        board.get_all_moves(|_, mov| nodes.push(Node::new(mov, mover)));
+        let n_children = (self.nodes.len() - first) as u8;
+        let node = &mut self.nodes[node];
+        node.first_child = first as u32;
+        node.n_children = n_children;
+        node.expanded = true;
+    }
+
+    fn result(&self, iterations: usize) -> SearchResult {
+        let root = &self.nodes[0];
+        let children: Vec<_> = root
+            .children()
+            .map(|index| {
+                let child = &self.nodes[index];
+                ChildStats {
+                    pos: child.mov.pos(),
+                    visits: child.visits,
+                    value: child.value(),
+                }
+            })
+            .collect();
+        let best = children
+            .iter()
+            .max_by_key(|child| child.visits)
+            .map(|child| child.pos);
+        SearchResult {
+            best,
+            children,
+            iterations,
+        }
+    }
+}
+
+/// Plays uniformly random moves until the game ends; returns the winner.
+pub fn playout(mut board: Bitboard, rng: &mut Rng) -> Option<Player> {
+    while !board.game_over() {
+        let mask = board.legal_mask();
+        let total: u32 = mask.iter().map(|m| m.count_ones()).sum();
+        let mut k = rng.below(total as usize) as u32;
+        for (field, &bits) in mask.iter().enumerate() {
+            let n = bits.count_ones();
+            if k < n {
+                let mut bits = bits;
+                for _ in 0..k {
+                    bits &= bits - 1;
+                }
+                let square = bits.trailing_zeros() as Index;
+                if let Some(pos) = Pos::new(field as Index, square) {
+                    // SAFETY: taken from the legal mask
+                    unsafe { board.make_move_unchecked(pos) };
+                }
+                break;
+            }
+            k -= n;
+        }
+    }
+    board.winner()
+}
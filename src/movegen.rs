@@ -0,0 +1,237 @@
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+
+use crate::board::{Bitboard, Bits, Index, Move, Pos};
+
+/// Order in which [`Bitboard::get_all_moves_ordered`] visits fields and squares;
+/// search picks one with [`Searcher::set_move_order`](crate::search::Searcher::set_move_order).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MoveOrder {
+    /// Fields ascending, squares ascending.
+    #[default]
+    Natural,
+    /// Center first, then corners, then edges, for both fields and squares.
+    CenterFirst,
+}
+
+impl MoveOrder {
+    #[inline(always)]
+    pub fn indices(self) -> &'static [Index; 9] {
+        match self {
+            MoveOrder::Natural => &[0, 1, 2, 3, 4, 5, 6, 7, 8],
+            MoveOrder::CenterFirst => &[4, 0, 2, 6, 8, 1, 3, 5, 7],
+        }
+    }
+}
+
+impl fmt::Display for MoveOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MoveOrder::Natural => "natural",
+            MoveOrder::CenterFirst => "center-first",
+        })
+    }
+}
+
+impl FromStr for MoveOrder {
+    type Err = ParseMoveOrderError;
+
+    fn from_str(s: &str) -> Result<MoveOrder, ParseMoveOrderError> {
+        match s {
+            "natural" => Ok(MoveOrder::Natural),
+            "center-first" => Ok(MoveOrder::CenterFirst),
+            _ => Err(ParseMoveOrderError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMoveOrderError(String);
+
+impl fmt::Display for ParseMoveOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown move order: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoveOrderError {}
+
+/// Fixed-capacity, stack-allocated list of moves; no position has more than 81.
+#[derive(Copy, Clone)]
+pub struct MoveList {
+    moves: [Move; 81],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [Move::NULL; 81],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// # Panics
+    ///
+    /// If the list already holds 81 moves.
+    pub fn push(&mut self, mov: Move) {
+        self.moves[self.len] = mov;
+        self.len += 1;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over legal moves in natural order, see [`Bitboard::moves`].
+#[derive(Clone, Debug)]
+pub struct Moves {
+    mask: [Bits; 9],
+    field: usize,
+    remaining: usize,
+}
+
+impl Iterator for Moves {
+    type Item = Pos;
+
+    fn next(&mut self) -> Option<Pos> {
+        while self.field < 9 {
+            let bits = &mut self.mask[self.field];
+            if *bits != 0 {
+                let square = bits.trailing_zeros();
+                *bits &= *bits - 1;
+                self.remaining -= 1;
+                return Some(Pos {
+                    field: self.field as Index,
+                    square: 1 << square,
+                });
+            }
+            self.field += 1;
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Moves {}
+
+impl FusedIterator for Moves {}
+
+impl Bitboard {
+    /// Legal moves in the same order as [`Bitboard::get_all_moves`].
+    pub fn moves(&self) -> Moves {
+        let mask = self.legal_mask();
+        Moves {
+            mask,
+            field: 0,
+            remaining: mask.iter().map(|m| m.count_ones() as usize).sum(),
+        }
+    }
+
+    /// Replaces the contents of `list` with all legal moves in natural order.
+    pub fn legal_moves(&self, list: &mut MoveList) {
+        self.legal_moves_ordered(MoveOrder::Natural, list)
+    }
+
+    pub fn legal_moves_ordered(&self, order: MoveOrder, list: &mut MoveList) {
+        list.clear();
+        if self.game_over() {
+            return;
+        }
+        let mut board = *self;
+        board.get_all_moves_ordered(order, |_, mov| list.push(mov));
+    }
+
+    /// Calls `f` for every legal move, field-major in ascending field order and
+    /// squares ascending within each field; perft relies on this contract, and
+    /// search too unless given another [`MoveOrder`].
+    ///
+    /// The board must still be in play: to keep the hot path free of the
+    /// check, a finished game still yields the empty squares of its open
+    /// fields. [`Bitboard::moves`] and [`Bitboard::legal_moves`] yield none.
+    pub fn get_all_moves<F: FnMut(&mut Bitboard, Move)>(&mut self, f: F) {
+        self.get_all_moves_ordered(MoveOrder::Natural, f)
+    }
+
+    #[inline(always)]
+    pub fn get_all_moves_ordered<F: FnMut(&mut Bitboard, Move)>(
+        &mut self,
+        order: MoveOrder,
+        mut f: F,
+    ) {
+        let indices = order.indices();
+        let valid_field = self.valid_field;
+        let all_valid = valid_field.is_none();
+        let available_fields = match valid_field {
+            Some(ref field) => std::slice::from_ref(field),
+            _ => &indices[..],
+        };
+        let meta_field = self.get_meta_field(self.turn);
+        let n_blocked = self.n_blocked;
+        let last_move = self.last_move;
+        let hash = self.hash as u32;
+        for &field in available_fields {
+            if self.is_closed(field) {
+                continue;
+            }
+            let field_status = self.get_field_status(field);
+            let (white, black) = self.get_fields(field);
+            let any = white | black;
+            for &square in indices {
+                let square = 1 << square;
+                let taken = any & square != 0;
+                if taken {
+                    continue;
+                }
+                let pos = Pos { field, square };
+                f(
+                    self,
+                    Move {
+                        pos,
+                        all_valid,
+                        field_status,
+                        meta_field,
+                        n_blocked,
+                        last_move,
+                        hash,
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Move notation: `<field>-<square>`, both numbered 1 to 9 row-major from the
+//! top left, so `5-5` is the center of the center field. The separator may
+//! also be `/` or left out (`55`).
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::{Bitboard, Move, MoveError, Pos};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParsePosError {
+    /// Not two digits with an optional separator.
+    Malformed,
+    /// A field or square digit outside `1..=9`.
+    OutOfRange(char),
+    /// Well-formed but not a legal move in the given position.
+    Illegal(Pos, MoveError),
+}
+
+impl fmt::Display for ParsePosError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsePosError::Malformed => write!(f, "expected a move like 5-5"),
+            ParsePosError::OutOfRange(ch) => write!(f, "expected a digit 1-9, got {:?}", ch),
+            ParsePosError::Illegal(pos, err) => write!(f, "illegal move {}: {}", pos, err),
+        }
+    }
+}
+
+impl std::error::Error for ParsePosError {}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.field() + 1, self.square() + 1)
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.pos().fmt(f)
+    }
+}
+
+fn digit(ch: char) -> Result<u8, ParsePosError> {
+    match ch {
+        '1'..='9' => Ok(ch as u8 - b'1'),
+        _ => Err(ParsePosError::OutOfRange(ch)),
+    }
+}
+
+impl FromStr for Pos {
+    type Err = ParsePosError;
+
+    fn from_str(s: &str) -> Result<Pos, ParsePosError> {
+        let chars: Vec<char> = s.trim().chars().collect();
+        let (field, square) = match chars.as_slice() {
+            &[field, square] | &[field, '-', square] | &[field, '/', square] => (field, square),
+            _ => return Err(ParsePosError::Malformed),
+        };
+        Pos::new(digit(field)?, digit(square)?).ok_or(ParsePosError::Malformed)
+    }
+}
+
+impl Bitboard {
+    /// Parses a move and checks that it is legal for the side to move.
+    pub fn parse_move(&self, s: &str) -> Result<Pos, ParsePosError> {
+        let pos: Pos = s.parse()?;
+        self.check_move(pos)
+            .map(|()| pos)
+            .map_err(|err| ParsePosError::Illegal(pos, err))
+    }
+}
+
+/// Plays `moves` from the starting position.
+pub fn parse_moves<'a, I>(moves: I) -> Result<Bitboard, ParsePosError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    parse_moves_from(Bitboard::new(), moves)
+}
+
+/// Plays `moves` from `board`, e.g. the start of a game under other rules.
+pub fn parse_moves_from<'a, I>(mut board: Bitboard, moves: I) -> Result<Bitboard, ParsePosError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    for mov in moves {
+        let pos = board.parse_move(mov)?;
+        board.make_move(pos);
+    }
+    Ok(board)
+}
@@ -0,0 +1,112 @@
+//! Opening names for the first few plies of a game.
+//!
+//! Lines are read from a small TOML subset: `#` comments, an optional
+//! `[openings]` header and `"name" = "moves"` pairs, one per line. The
+//! built-in table can be replaced by a file of the same format with
+//! [`Openings::load`] (`--openings` on the command line).
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+
+use crate::board::Pos;
+use crate::notation::{parse_moves, ParsePosError};
+use crate::symmetry::SYMMETRIES;
+
+/// The built-in table, see `src/openings.toml`.
+pub static OPENINGS: Lazy<Openings> =
+    Lazy::new(|| Openings::parse(include_str!("openings.toml")).expect("built-in openings parse"));
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub name: String,
+    pub moves: Vec<Pos>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseOpeningsError {
+    /// Line number (from 1) that is not a comment, header or pair.
+    Syntax(usize),
+    /// Line number whose moves do not parse or are illegal.
+    Move(usize, ParsePosError),
+}
+
+impl fmt::Display for ParseOpeningsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseOpeningsError::Syntax(line) => {
+                write!(f, "line {}: expected \"name\" = \"moves\"", line)
+            }
+            ParseOpeningsError::Move(line, err) => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+impl std::error::Error for ParseOpeningsError {}
+
+fn unquote(s: &str) -> Option<&str> {
+    let s = s.trim();
+    s.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Openings {
+    lines: Vec<Opening>,
+}
+
+impl Openings {
+    pub fn parse(s: &str) -> Result<Openings, ParseOpeningsError> {
+        let mut lines = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "[openings]" {
+                continue;
+            }
+            let syntax = ParseOpeningsError::Syntax(i + 1);
+            let (name, moves) = line.split_once('=').ok_or(syntax)?;
+            let (name, moves) = (unquote(name).ok_or(syntax)?, unquote(moves).ok_or(syntax)?);
+            if name.is_empty() {
+                return Err(syntax);
+            }
+            parse_moves(moves.split_whitespace())
+                .map_err(|err| ParseOpeningsError::Move(i + 1, err))?;
+            lines.push(Opening {
+                name: name.to_string(),
+                moves: moves
+                    .split_whitespace()
+                    .filter_map(|m| m.parse().ok())
+                    .collect(),
+            });
+        }
+        Ok(Openings { lines })
+    }
+
+    /// Reads a table in the format above from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Openings> {
+        let text = fs::read_to_string(path)?;
+        Openings::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn lines(&self) -> &[Opening] {
+        &self.lines
+    }
+
+    /// The longest line that the game `moves` starts with, up to symmetry.
+    pub fn classify(&self, moves: &[Pos]) -> Option<&Opening> {
+        self.lines
+            .iter()
+            .filter(|line| {
+                line.moves.len() <= moves.len()
+                    && (0..SYMMETRIES).any(|sym| {
+                        line.moves
+                            .iter()
+                            .zip(moves)
+                            .all(|(&a, &b)| a.transformed(sym) == b)
+                    })
+            })
+            .max_by_key(|line| line.moves.len())
+    }
+}
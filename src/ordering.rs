@@ -0,0 +1,87 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::{Index, Pos};
+
+// center, corners, edges
+pub const DEFAULT_FIELD_WEIGHTS: [u8; 9] = [2, 1, 2, 1, 3, 1, 2, 1, 2];
+pub const DEFAULT_SQUARE_WEIGHTS: [u8; 9] = [2, 1, 2, 1, 3, 1, 2, 1, 2];
+
+/// Static priority of every (field, square) pair, higher is better; used as
+/// the final tiebreaker when ordering moves, see
+/// [`Searcher::set_move_table`](crate::search::Searcher::set_move_table).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveTable {
+    priority: [[u8; 9]; 9],
+}
+
+impl Default for MoveTable {
+    fn default() -> MoveTable {
+        MoveTable::new(DEFAULT_FIELD_WEIGHTS, DEFAULT_SQUARE_WEIGHTS)
+    }
+}
+
+impl MoveTable {
+    /// Field weights dominate: any square of a heavier field is tried before
+    /// any square of a lighter one.
+    pub fn new(field_weights: [u8; 9], square_weights: [u8; 9]) -> MoveTable {
+        let scale = square_weights.iter().max().map_or(1, |&w| w as u16 + 1);
+        let mut priority = [[0; 9]; 9];
+        for (field, row) in priority.iter_mut().enumerate() {
+            for (square, p) in row.iter_mut().enumerate() {
+                let value = field_weights[field] as u16 * scale + square_weights[square] as u16;
+                *p = value.min(u8::MAX as u16) as u8;
+            }
+        }
+        MoveTable { priority }
+    }
+
+    pub fn get(&self, field: Index, square: Index) -> u8 {
+        self.priority[field as usize % 9][square as usize % 9]
+    }
+
+    pub fn priority(&self, pos: Pos) -> u8 {
+        self.get(pos.field(), pos.square())
+    }
+}
+
+/// Parses nine field weights and nine square weights, in board order and
+/// separated by a slash, e.g. `2,1,2,1,3,1,2,1,2/2,1,2,1,3,1,2,1,2` for the
+/// default table.
+impl FromStr for MoveTable {
+    type Err = ParseWeightsError;
+
+    fn from_str(s: &str) -> Result<MoveTable, ParseWeightsError> {
+        let err = || ParseWeightsError(s.to_string());
+        let parse = |part: &str| -> Result<[u8; 9], ParseWeightsError> {
+            let mut weights = [0; 9];
+            let mut values = part.split(',').map(|w| w.trim().parse::<u8>());
+            for w in weights.iter_mut() {
+                *w = values.next().and_then(Result::ok).ok_or_else(err)?;
+            }
+            match values.next() {
+                None => Ok(weights),
+                Some(_) => Err(err()),
+            }
+        };
+        match s.split_once('/') {
+            Some((fields, squares)) => Ok(MoveTable::new(parse(fields)?, parse(squares)?)),
+            None => Err(err()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseWeightsError(String);
+
+impl fmt::Display for ParseWeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid move weights {:?}: expected 9 field and 9 square weights (0-255) separated by '/'",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseWeightsError {}
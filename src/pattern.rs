@@ -0,0 +1,128 @@
+use std::fmt;
+
+use crate::board::{Bitboard, Bits, Index, Player, ALL_FIELDS};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Own,
+    Opp,
+    Empty,
+    Any,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Masks {
+    own: Bits,
+    opp: Bits,
+    empty: Bits,
+}
+
+// Maps square (row, col) under one of the 8 symmetries of the square:
+// rotate `sym % 4` times clockwise, then mirror horizontally if `sym >= 4`.
+pub(crate) fn transform_square(square: usize, sym: usize) -> usize {
+    let (mut r, mut c) = (square / 3, square % 3);
+    for _ in 0..sym % 4 {
+        let t = r;
+        r = c;
+        c = 2 - t;
+    }
+    if sym >= 4 {
+        c = 2 - c;
+    }
+    r * 3 + c
+}
+
+pub(crate) fn transform(bits: Bits, sym: usize) -> Bits {
+    (0..9)
+        .filter(|&s| bits & (1 << s) != 0)
+        .fold(0, |acc, s| acc | (1 << transform_square(s, sym)))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParsePatternError {
+    InvalidCell(char),
+    WrongLength(usize),
+}
+
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsePatternError::InvalidCell(ch) => write!(f, "invalid pattern cell: {:?}", ch),
+            ParsePatternError::WrongLength(n) => write!(f, "expected 9 pattern cells, got {}", n),
+        }
+    }
+}
+
+impl std::error::Error for ParsePatternError {}
+
+/// A 3x3 local pattern, matched modulo the 8 local symmetries.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    variants: Vec<Masks>,
+}
+
+impl Pattern {
+    pub fn new(cells: [Cell; 9]) -> Pattern {
+        let mut masks = Masks::default();
+        for (square, cell) in cells.iter().enumerate() {
+            let bit = 1 << square;
+            match cell {
+                Cell::Own => masks.own |= bit,
+                Cell::Opp => masks.opp |= bit,
+                Cell::Empty => masks.empty |= bit,
+                Cell::Any => {}
+            }
+        }
+        let mut variants: Vec<Masks> = Vec::with_capacity(8);
+        for sym in 0..8 {
+            let variant = Masks {
+                own: transform(masks.own, sym),
+                opp: transform(masks.opp, sym),
+                empty: transform(masks.empty, sym),
+            };
+            if !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+        Pattern { variants }
+    }
+
+    /// Parses 9 cells row by row: `x` own, `o` opponent, `.` empty, `?` any;
+    /// whitespace and `/` separators are ignored, e.g. `"x.o/.x./..?"`.
+    pub fn parse(s: &str) -> Result<Pattern, ParsePatternError> {
+        let mut cells = [Cell::Any; 9];
+        let mut n = 0;
+        for ch in s.chars().filter(|&ch| !ch.is_whitespace() && ch != '/') {
+            let cell = match ch.to_ascii_lowercase() {
+                'x' => Cell::Own,
+                'o' => Cell::Opp,
+                '.' => Cell::Empty,
+                '?' => Cell::Any,
+                _ => return Err(ParsePatternError::InvalidCell(ch)),
+            };
+            if n < 9 {
+                cells[n] = cell;
+            }
+            n += 1;
+        }
+        if n == 9 {
+            Ok(Pattern::new(cells))
+        } else {
+            Err(ParsePatternError::WrongLength(n))
+        }
+    }
+
+    pub fn matches(&self, own: Bits, opp: Bits) -> bool {
+        let empty = !(own | opp) & ALL_FIELDS;
+        self.variants
+            .iter()
+            .any(|m| own & m.own == m.own && opp & m.opp == m.opp && empty & m.empty == m.empty)
+    }
+
+    /// Meta-field style mask of local fields matching the pattern from `p`'s perspective.
+    pub fn find(&self, board: &Bitboard, p: Player) -> Bits {
+        (0..9 as Index)
+            .filter(|&field| self.matches(board.get(p, field), board.get(p.other(), field)))
+            .fold(0, |acc, field| acc | (1 << field))
+    }
+}
@@ -0,0 +1,226 @@
+use crate::board::{Bitboard, Pos};
+use crate::cancel::{CancelToken, Cancelled};
+
+/// Perft counts from the starting position, indexed by depth.
+pub const STARTPOS_PERFT: [u64; 9] = [
+    1, 81, 720, 6336, 55080, 473256, 4020960, 33782544, 281067408,
+];
+
+/// Number of positions exactly `depth` plies below `board`.
+pub fn perft(board: &mut Bitboard, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if board.game_over() {
+        return 0;
+    }
+    let mut sum = 0;
+    if depth == 1 {
+        board.get_all_moves(|_, _| sum += 1);
+    } else {
+        board.get_all_moves(|b, mov| unsafe {
+            b.make_move_unchecked(mov.pos);
+            sum += perft(b, depth - 1);
+            b.undo_move_unchecked(&mov);
+        });
+    }
+    sum
+}
+
+// subtrees this shallow finish in microseconds, so need no cancellation check
+const CANCEL_CHECK_DEPTH: usize = 4;
+
+/// [`perft`] that gives up once `cancel` fires.
+pub fn perft_cancellable(
+    board: &mut Bitboard,
+    depth: usize,
+    cancel: &CancelToken,
+) -> Result<u64, Cancelled> {
+    if depth < CANCEL_CHECK_DEPTH || board.game_over() {
+        return Ok(perft(board, depth));
+    }
+    if cancel.is_cancelled() {
+        return Err(Cancelled);
+    }
+    let mut sum = Ok(0);
+    board.get_all_moves(|b, mov| {
+        if let Ok(total) = sum {
+            // SAFETY: `mov` was just generated for `b`
+            unsafe { b.make_move_unchecked(mov.pos) };
+            sum = perft_cancellable(b, depth - 1, cancel).map(|n| total + n);
+            unsafe { b.undo_move_unchecked(&mov) };
+        }
+    });
+    sum
+}
+
+// (position, depth) -> count; the depth sits in the top byte of
+// `count_depth`, which is zero for an empty entry
+#[derive(Copy, Clone, Default)]
+struct PerftEntry {
+    key: u64,
+    count_depth: u64,
+}
+
+impl PerftEntry {
+    fn depth(self) -> usize {
+        (self.count_depth >> 56) as usize
+    }
+}
+
+// shallower subtrees are cheaper to count than to look up
+const MIN_HASHED_DEPTH: usize = 4;
+
+const BUCKET_SIZE: usize = 4;
+
+// one bucket per cache line, like the search table's
+#[repr(align(64))]
+#[derive(Copy, Clone, Default)]
+struct PerftBucket([PerftEntry; BUCKET_SIZE]);
+
+struct PerftTable {
+    buckets: Vec<PerftBucket>,
+}
+
+impl PerftTable {
+    fn new(mb: usize) -> PerftTable {
+        let n = ((mb.max(1) << 20) / std::mem::size_of::<PerftBucket>()).max(1);
+        let n = 1 << (usize::BITS - 1 - n.leading_zeros());
+        PerftTable {
+            buckets: vec![PerftBucket::default(); n],
+        }
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        key as usize & (self.buckets.len() - 1)
+    }
+
+    fn probe(&self, key: u64, depth: usize) -> Option<u64> {
+        self.buckets[self.bucket(key)]
+            .0
+            .iter()
+            .find(|e| e.key == key && e.depth() == depth)
+            .map(|e| e.count_depth & ((1 << 56) - 1))
+    }
+
+    // replaces an empty slot, or else the shallowest count: deeper ones
+    // save more work when they are hit again
+    fn store(&mut self, key: u64, depth: usize, count: u64) {
+        let index = self.bucket(key);
+        let slot = self.buckets[index]
+            .0
+            .iter_mut()
+            .min_by_key(|e| e.depth())
+            .expect("buckets are not empty");
+        *slot = PerftEntry {
+            key,
+            count_depth: (depth as u64) << 56 | count,
+        };
+    }
+
+    fn perft(
+        &mut self,
+        board: &mut Bitboard,
+        depth: usize,
+        cancel: &CancelToken,
+    ) -> Result<u64, Cancelled> {
+        if depth < MIN_HASHED_DEPTH || board.game_over() {
+            return Ok(perft(board, depth));
+        }
+        let key = board.hash();
+        if let Some(count) = self.probe(key, depth) {
+            return Ok(count);
+        }
+        if cancel.is_cancelled() {
+            return Err(Cancelled);
+        }
+        let mut sum = Ok(0);
+        board.get_all_moves(|b, mov| {
+            if let Ok(total) = sum {
+                #[cfg(feature = "check-undo")]
+                let snapshot = *b;
+                // SAFETY: `mov` was just generated for `b`
+                unsafe { b.make_move_unchecked(mov.pos) };
+                sum = self.perft(b, depth - 1, cancel).map(|n| total + n);
+                unsafe { b.undo_move_unchecked(&mov) };
+                #[cfg(feature = "check-undo")]
+                snapshot.assert_restored(b, mov.pos);
+            }
+        });
+        let sum = sum?;
+        self.store(key, depth, sum);
+        Ok(sum)
+    }
+}
+
+/// [`perft`] with a transposition table of about `mb` megabytes, keeping
+/// the deepest counts; positions reached by different move orders are only
+/// counted once per depth.
+///
+/// Forced fields make transpositions rare (8% of the depth-7 leaves are
+/// duplicates), so this is not a large speedup: through depth 9 it runs
+/// within a few percent of plain [`perft`], and the share of transpositions
+/// only grows slowly with depth. Gives up once `cancel` fires.
+pub fn perft_hashed(
+    board: &mut Bitboard,
+    depth: usize,
+    mb: usize,
+    cancel: &CancelToken,
+) -> Result<u64, Cancelled> {
+    PerftTable::new(mb).perft(board, depth, cancel)
+}
+
+/// Perft split by root move, in move generation order; the counts sum to
+/// `perft(board, depth)` for `depth >= 1`. Gives up once `cancel` fires.
+pub fn perft_divide(
+    board: &mut Bitboard,
+    depth: usize,
+    cancel: &CancelToken,
+) -> Result<Vec<(Pos, u64)>, Cancelled> {
+    let mut divide = Vec::new();
+    if depth == 0 || board.game_over() {
+        return Ok(divide);
+    }
+    let mut result = Ok(());
+    board.get_all_moves(|b, mov| {
+        if result.is_ok() {
+            b.make_move(mov.pos);
+            result = perft_cancellable(b, depth - 1, cancel).map(|n| divide.push((mov.pos, n)));
+            b.undo_move(&mov);
+        }
+    });
+    result.map(|()| divide)
+}
+
+/// Total number of positions 1 to `depth + 1` plies below `board`.
+pub fn move_gen_impl(board: &mut Bitboard, depth: usize) -> usize {
+    if board.game_over() {
+        0
+    } else {
+        let mut sum = 0;
+        if depth != 0 {
+            board.get_all_moves(|b, mov| {
+                #[cfg(feature = "check-undo")]
+                let snapshot = *b;
+                // SAFETY: `mov` was just generated for `b`
+                unsafe {
+                    b.make_move_unchecked(mov.pos);
+                    sum += 1 + move_gen_impl(b, depth - 1);
+                    b.undo_move_unchecked(&mov);
+                }
+                #[cfg(feature = "check-undo")]
+                snapshot.assert_restored(b, mov.pos);
+            });
+        } else {
+            board.get_all_moves(|_, _| {
+                sum += 1;
+            })
+        }
+        sum
+    }
+}
+
+/// [`move_gen_impl`] from the starting position.
+pub fn move_gen(depth: usize) -> usize {
+    move_gen_impl(&mut Bitboard::new(), depth)
+}
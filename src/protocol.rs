@@ -0,0 +1,385 @@
+//! UCI-like text protocol over stdin/stdout.
+//!
+//! Supported commands: `uci`, `isready`, `setoption name Hash value <mb>`,
+//! `setoption name KeepHash value <true|false>` (keep the table across
+//! `ucinewgame`), `setoption name SymmetricHash value <true|false>` (see
+//! [`Searcher::set_symmetric_tt`]), `setoption name TieBreak value
+//! <none|fields>` (see [`crate::rules::TieBreak`]), `setoption name WonFields
+//! value <closed|playable>` (see [`crate::rules::WonFields`]), `setoption
+//! name MoveWeights value <fields>/<squares>` (see
+//! [`Searcher::set_move_table`]), `setoption name MoveOrder value
+//! <natural|center-first>` (see [`Searcher::set_move_order`]), `ucinewgame`,
+//! `position startpos [moves ...]`, `go [depth <n>] [nodes <n>] [movetime
+//! <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [infinite]`,
+//! `stop` and `quit`. Moves use the notation of [`crate::notation`].
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::{Bitboard, Player};
+use crate::cancel::CancelToken;
+pub use crate::clock::allot_time;
+use crate::clock::{Clock, TimeControl};
+use crate::eval::Score;
+use crate::movegen::MoveOrder;
+use crate::notation::parse_moves_from;
+use crate::ordering::MoveTable;
+use crate::refutation::Refutation;
+use crate::rules::Rules;
+use crate::search::{is_win_score, SearchLimits, SearchResult, Searcher, MAX_PLY, WIN_SCORE};
+use crate::tt::Bound;
+use crate::version;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 4096;
+
+fn format_score(score: Score, bound: Bound) -> String {
+    let score = if is_win_score(score) {
+        // plies to the end of the game, like `mate` in UCI but not halved
+        let plies = WIN_SCORE - score.abs();
+        format!("mate {}", if score > 0 { plies } else { -plies })
+    } else {
+        format!("cp {}", score)
+    };
+    match bound {
+        Bound::Exact => score,
+        Bound::Lower => score + " lowerbound",
+        Bound::Upper => score + " upperbound",
+    }
+}
+
+pub fn format_info(result: &SearchResult) -> String {
+    let millis = result.elapsed.as_millis() as u64;
+    let nps = result.nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = result.pv.iter().map(|pos| pos.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth,
+        format_score(result.score, result.bound),
+        result.nodes,
+        nps,
+        millis,
+        pv.join(" ")
+    )
+}
+
+/// `info refutation` line for one root move of a [`RefutationTable`].
+///
+/// [`RefutationTable`]: crate::refutation::RefutationTable
+pub fn format_refutation(refutation: &Refutation) -> String {
+    let line: Vec<String> = refutation.line.iter().map(|pos| pos.to_string()).collect();
+    format!(
+        "info refutation {} score {} line {}",
+        refutation.pos,
+        format_score(refutation.score, Bound::Exact),
+        line.join(" ")
+    )
+}
+
+// the value after `name`, `Err` if it is missing or malformed
+fn token<T: std::str::FromStr>(tokens: &[&str], name: &str) -> Result<Option<T>, String> {
+    let i = match tokens.iter().position(|&t| t == name) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let value = tokens.get(i + 1).copied().unwrap_or_default();
+    match value.parse() {
+        Ok(value) => Ok(Some(value)),
+        Err(_) => Err(format!("invalid {} value: {:?}", name, value)),
+    }
+}
+
+fn millis(tokens: &[&str], name: &str) -> Result<Option<Duration>, String> {
+    Ok(token(tokens, name)?.map(Duration::from_millis))
+}
+
+/// Search limits for the arguments of a `go` command; `wtime`/`winc` are
+/// X's clock and `btime`/`binc` O's. A malformed value, or a depth outside
+/// `1..=`[`MAX_PLY`], is an error.
+pub fn parse_go(tokens: &[&str], side: Player) -> Result<SearchLimits, String> {
+    if tokens.contains(&"infinite") {
+        return Ok(SearchLimits::default());
+    }
+    let (time, inc) = match side {
+        Player::X => ("wtime", "winc"),
+        Player::O => ("btime", "binc"),
+    };
+    let movetime = match millis(tokens, "movetime")? {
+        Some(movetime) => Some(movetime),
+        None => {
+            let increment = millis(tokens, inc)?.unwrap_or_default();
+            let mut clock = Clock::new(TimeControl::new(Duration::default(), increment));
+            millis(tokens, time)?.map(|remaining| {
+                clock.set_remaining(side, remaining);
+                clock.allot(side)
+            })
+        }
+    };
+    let depth = match token::<u64>(tokens, "depth")? {
+        Some(depth) if !(1..=MAX_PLY as u64).contains(&depth) => {
+            return Err(format!("depth {} out of range 1..={}", depth, MAX_PLY));
+        }
+        depth => depth.map(|depth| depth as u8),
+    };
+    Ok(SearchLimits {
+        depth,
+        nodes: token(tokens, "nodes")?,
+        movetime,
+    })
+}
+
+/// Board for the arguments of a `position` command, `None` if malformed.
+pub fn parse_position(tokens: &[&str], rules: Rules) -> Option<Bitboard> {
+    let start = Bitboard::with_rules(rules);
+    match tokens {
+        ["startpos"] => Some(start),
+        ["startpos", "moves", moves @ ..] => parse_moves_from(start, moves.iter().copied()).ok(),
+        _ => None,
+    }
+}
+
+/// Where an [`Engine`] writes its replies, one line per write.
+pub type Output = Arc<Mutex<dyn Write + Send>>;
+
+fn say(out: &Output, line: fmt::Arguments) {
+    let mut out = out.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    // a closed output is no reason for the engine to stop
+    let _ = writeln!(out, "{}", line).and_then(|()| out.flush());
+}
+
+macro_rules! say {
+    ($out:expr, $($arg:tt)*) => {
+        say(&$out, format_args!($($arg)*))
+    };
+}
+
+/// Protocol state; a search runs on its own thread so that `stop` is heard.
+pub struct Engine {
+    board: Bitboard,
+    searcher: Option<Searcher>,
+    keep_hash: bool,
+    symmetric_hash: bool,
+    move_table: MoveTable,
+    move_order: MoveOrder,
+    rules: Rules,
+    cancel: CancelToken,
+    thinking: Option<JoinHandle<Searcher>>,
+    out: Output,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine::with_output(Arc::new(Mutex::new(io::stdout())))
+    }
+
+    /// An engine writing its replies, including those of searches running
+    /// in the background, to `out`.
+    pub fn with_output(out: Output) -> Engine {
+        Engine {
+            board: Bitboard::new(),
+            searcher: Some(Searcher::new(DEFAULT_HASH_MB)),
+            cancel: CancelToken::default(),
+            keep_hash: false,
+            symmetric_hash: false,
+            move_table: MoveTable::default(),
+            move_order: MoveOrder::Natural,
+            rules: Rules::default(),
+            thinking: None,
+            out,
+        }
+    }
+
+    /// Handles one input line; returns `false` on `quit`. Only `stop` and
+    /// `quit` interrupt a running search, other commands wait for it.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [] => {}
+            ["uci"] => {
+                say!(self.out, "id name {}", version::id());
+                say!(self.out, "id author {}", version::AUTHORS);
+                // the rest of the version report, past the id and authors
+                for line in version::report().lines().skip(2) {
+                    say!(self.out, "info string {}", line);
+                }
+                say!(
+                    self.out,
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB,
+                    MAX_HASH_MB
+                );
+                say!(self.out, "option name KeepHash type check default false");
+                say!(
+                    self.out,
+                    "option name SymmetricHash type check default false"
+                );
+                say!(
+                    self.out,
+                    "option name TieBreak type combo default none var none var fields"
+                );
+                say!(
+                    self.out,
+                    "option name WonFields type combo default closed var closed var playable"
+                );
+                say!(
+                    self.out,
+                    "option name MoveWeights type string default 2,1,2,1,3,1,2,1,2/2,1,2,1,3,1,2,1,2"
+                );
+                say!(
+                    self.out,
+                    "option name MoveOrder type combo default natural var natural var center-first"
+                );
+                say!(self.out, "uciok");
+            }
+            ["isready"] => say!(self.out, "readyok"),
+            ["setoption", "name", "Hash", "value", value] => match value.parse() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => {
+                    self.wait();
+                    let mut searcher = Searcher::new(mb);
+                    searcher.set_symmetric_tt(self.symmetric_hash);
+                    searcher.set_move_table(self.move_table.clone());
+                    searcher.set_move_order(self.move_order);
+                    self.searcher = Some(searcher);
+                }
+                _ => say!(self.out, "info string invalid Hash value: {}", value),
+            },
+            ["setoption", "name", "KeepHash", "value", value] => match *value {
+                "true" => self.keep_hash = true,
+                "false" => self.keep_hash = false,
+                _ => say!(self.out, "info string invalid KeepHash value: {}", value),
+            },
+            ["setoption", "name", "SymmetricHash", "value", value] => match value.parse() {
+                Ok(symmetric) => {
+                    self.wait();
+                    self.symmetric_hash = symmetric;
+                    let searcher = self.searcher_mut();
+                    searcher.set_symmetric_tt(symmetric);
+                    searcher.new_game(false);
+                }
+                _ => say!(
+                    self.out,
+                    "info string invalid SymmetricHash value: {}",
+                    value
+                ),
+            },
+            ["setoption", "name", "TieBreak", "value", value] => match value.parse() {
+                Ok(tie_break) => {
+                    // terminal scores differ between tie-breaks
+                    self.wait();
+                    self.rules.tie_break = tie_break;
+                    self.searcher_mut().new_game(false);
+                }
+                Err(err) => say!(self.out, "info string {}", err),
+            },
+            ["setoption", "name", "WonFields", "value", value] => match value.parse() {
+                Ok(won_fields) => {
+                    // entries searched under other rules would be reused
+                    self.wait();
+                    self.rules.won_fields = won_fields;
+                    self.searcher_mut().new_game(false);
+                }
+                Err(err) => say!(self.out, "info string {}", err),
+            },
+            ["setoption", "name", "MoveWeights", "value", value] => match value.parse() {
+                Ok(table) => {
+                    self.wait();
+                    self.move_table = table;
+                    let table = self.move_table.clone();
+                    self.searcher_mut().set_move_table(table);
+                }
+                Err(err) => say!(self.out, "info string {}", err),
+            },
+            ["setoption", "name", "MoveOrder", "value", value] => match value.parse() {
+                Ok(order) => {
+                    self.wait();
+                    self.move_order = order;
+                    self.searcher_mut().set_move_order(order);
+                }
+                Err(err) => say!(self.out, "info string {}", err),
+            },
+            ["setoption", ..] => say!(self.out, "info string unknown option: {}", line.trim()),
+            ["ucinewgame"] => {
+                self.wait();
+                self.board = Bitboard::with_rules(self.rules);
+                let keep_hash = self.keep_hash;
+                self.searcher_mut().new_game(keep_hash);
+            }
+            ["position", args @ ..] => {
+                self.wait();
+                match parse_position(args, self.rules) {
+                    Some(board) => self.board = board,
+                    None => say!(self.out, "info string invalid position: {}", line.trim()),
+                }
+            }
+            ["go", args @ ..] => {
+                self.wait();
+                match parse_go(args, self.board.side_to_move()) {
+                    Ok(limits) => self.go(limits),
+                    Err(err) => say!(self.out, "info string error: {}", err),
+                }
+            }
+            ["stop"] => self.stop(),
+            ["quit"] => {
+                self.stop();
+                return false;
+            }
+            _ => say!(self.out, "info string unknown command: {}", line.trim()),
+        }
+        true
+    }
+
+    fn searcher_mut(&mut self) -> &mut Searcher {
+        self.searcher.as_mut().expect("no search running")
+    }
+
+    fn go(&mut self, limits: SearchLimits) {
+        let mut searcher = self.searcher.take().expect("no search running");
+        let board = self.board;
+        self.cancel = CancelToken::new();
+        searcher.set_cancel(self.cancel.clone());
+        let out = self.out.clone();
+        self.thinking = Some(thread::spawn(move || {
+            let result = searcher.think_with(&board, limits, |r| say!(out, "{}", format_info(r)));
+            match result.best {
+                Some(pos) => say!(out, "bestmove {}", pos),
+                None => say!(out, "bestmove none"),
+            }
+            searcher
+        }));
+    }
+
+    /// Aborts the running search, if any, and waits for its `bestmove`.
+    pub fn stop(&mut self) {
+        if self.thinking.is_some() {
+            self.cancel.cancel();
+            self.wait();
+        }
+    }
+
+    /// Waits for the running search, if any, to finish on its own.
+    pub fn wait(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            self.searcher = Some(thinking.join().expect("search thread panicked"));
+        }
+    }
+}
+
+/// Reads commands from stdin until `quit` or end of input.
+pub fn run() -> io::Result<()> {
+    let mut engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        if !engine.handle(&line?) {
+            return Ok(());
+        }
+    }
+    engine.wait();
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Why the other root moves are worse than the best one: a short search
+//! after each of them, kept so that an analysis front end can answer "why
+//! not this move?" without searching again.
+
+use crate::board::{Bitboard, Pos};
+use crate::eval::Score;
+use crate::search::Searcher;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Refutation {
+    /// The root move refuted.
+    pub pos: Pos,
+    /// Score after `pos` from the point of view of the side that played it.
+    pub score: Score,
+    /// The opponent's best reply and how the game goes on from there.
+    pub line: Vec<Pos>,
+}
+
+/// Refutations of every legal root move except the best one, best scoring
+/// first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefutationTable {
+    refutations: Vec<Refutation>,
+}
+
+impl RefutationTable {
+    /// Searches every legal move of `board` other than `best` to `depth`
+    /// plies (at least 1) with `searcher`, whose table the search of `best`
+    /// has usually warmed up already. The searches count as one with the
+    /// search before them, see [`Searcher::search_continued`].
+    pub fn build(
+        searcher: &mut Searcher,
+        board: &Bitboard,
+        best: Option<Pos>,
+        depth: u8,
+    ) -> RefutationTable {
+        let mut refutations: Vec<Refutation> = board
+            .moves()
+            .filter(|&pos| Some(pos) != best)
+            .map(|pos| {
+                let mut child = *board;
+                child.make_move(pos);
+                let reply = searcher.search_continued(&child, depth);
+                Refutation {
+                    pos,
+                    score: -reply.score,
+                    line: reply.pv,
+                }
+            })
+            .collect();
+        refutations.sort_by_key(|r| -r.score);
+        RefutationTable { refutations }
+    }
+
+    /// The refutation of root move `pos`; `None` for the best move or a
+    /// move that is not legal.
+    pub fn get(&self, pos: Pos) -> Option<&Refutation> {
+        self.refutations.iter().find(|r| r.pos == pos)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Refutation> {
+        self.refutations.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.refutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refutations.is_empty()
+    }
+}
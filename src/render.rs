@@ -0,0 +1,128 @@
+//! Text rendering of the full 9x9 board.
+//!
+//! Empty squares that are legal moves are highlighted, and next to the middle
+//! row of every band of fields the meta board shows won (`X`/`O`) and tied
+//! fields.
+
+use std::fmt;
+
+use crate::board::{Bitboard, FieldStatus, Index, Player};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Ascii,
+    /// Box-drawing separators and dots for empty squares.
+    Unicode,
+}
+
+struct Glyphs {
+    empty: char,
+    legal: char,
+    tied: char,
+    vertical: &'static str,
+    horizontal: &'static str,
+    cross: &'static str,
+}
+
+impl Charset {
+    fn glyphs(self) -> Glyphs {
+        match self {
+            Charset::Ascii => Glyphs {
+                empty: '.',
+                legal: '*',
+                tied: '=',
+                vertical: "|",
+                horizontal: "-",
+                cross: "+",
+            },
+            Charset::Unicode => Glyphs {
+                empty: '·',
+                legal: '∘',
+                tied: '═',
+                vertical: "│",
+                horizontal: "─",
+                cross: "┼",
+            },
+        }
+    }
+}
+
+/// A [`Bitboard`] formatted with a given [`Charset`].
+pub struct Render<'a> {
+    board: &'a Bitboard,
+    charset: Charset,
+}
+
+impl Bitboard {
+    pub fn render(&self, charset: Charset) -> Render<'_> {
+        Render {
+            board: self,
+            charset,
+        }
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.render(Charset::Ascii).fmt(f)
+    }
+}
+
+impl fmt::Display for Render<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (board, g) = (self.board, self.charset.glyphs());
+        let legal = board.legal_mask();
+        let cell = |field: usize, square: usize| {
+            let bit = 1 << square;
+            if board.stones(Player::X, field as Index) & bit != 0 {
+                'X'
+            } else if board.stones(Player::O, field as Index) & bit != 0 {
+                'O'
+            } else if legal[field] & bit != 0 {
+                g.legal
+            } else {
+                g.empty
+            }
+        };
+        let status = |field: usize| match board.field_status(field as Index) {
+            FieldStatus::Won0 => 'X',
+            FieldStatus::Won1 => 'O',
+            FieldStatus::Tied => g.tied,
+            FieldStatus::None => g.empty,
+        };
+        let bar = g.horizontal.repeat(7);
+        let separator = [bar.as_str(); 3].join(g.cross);
+        for band in 0..3 {
+            if band != 0 {
+                writeln!(f, "{}", separator)?;
+            }
+            for row in 0..3 {
+                for col in 0..3 {
+                    let field = band * 3 + col;
+                    if col != 0 {
+                        write!(f, " {}", g.vertical)?;
+                    }
+                    for square in row * 3..row * 3 + 3 {
+                        write!(f, " {}", cell(field, square))?;
+                    }
+                }
+                if row == 1 {
+                    let meta = (band * 3..band * 3 + 3).map(status);
+                    write!(f, "    ")?;
+                    for marker in meta {
+                        write!(f, " {}", marker)?;
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+        match (board.result(), board.forced_field()) {
+            (Some(result), _) => result.fmt(f),
+            (None, Some(field)) => {
+                write!(f, "{} to move in field {}", board.side_to_move(), field + 1)
+            }
+            (None, None) => write!(f, "{} to move anywhere", board.side_to_move()),
+        }
+    }
+}
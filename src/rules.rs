@@ -0,0 +1,91 @@
+//! Rule variants found in other implementations of the game, chosen per
+//! board with [`Bitboard::with_rules`](crate::Bitboard::with_rules).
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Who wins when every field is decided but no player has a meta line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TieBreak {
+    /// The game is a tie.
+    #[default]
+    None,
+    /// The player who won more fields wins, as on CodinGame; equal counts
+    /// are still a tie.
+    FieldsWon,
+}
+
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TieBreak::None => "none",
+            TieBreak::FieldsWon => "fields",
+        })
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<TieBreak, ParseRuleError> {
+        match s {
+            "none" => Ok(TieBreak::None),
+            "fields" => Ok(TieBreak::FieldsWon),
+            _ => Err(ParseRuleError(s.to_string())),
+        }
+    }
+}
+
+/// Whether stones can still be placed in a field once it is won.
+///
+/// Either way a player sent to a field that takes no more stones may play
+/// anywhere, and a field counts for the meta board with its first winner.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum WonFields {
+    /// Won fields are closed, like full ones.
+    #[default]
+    Closed,
+    /// Won fields stay playable until full, and the game goes on until a
+    /// meta line is made or every field is full.
+    Playable,
+}
+
+impl fmt::Display for WonFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WonFields::Closed => "closed",
+            WonFields::Playable => "playable",
+        })
+    }
+}
+
+impl FromStr for WonFields {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<WonFields, ParseRuleError> {
+        match s {
+            "closed" => Ok(WonFields::Closed),
+            "playable" => Ok(WonFields::Playable),
+            _ => Err(ParseRuleError(s.to_string())),
+        }
+    }
+}
+
+/// The default rules are the common ones: won fields are closed and a
+/// filled meta board without a line is a tie.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rules {
+    pub tie_break: TieBreak,
+    pub won_fields: WonFields,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRuleError(String);
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown rule variant: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
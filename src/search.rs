@@ -0,0 +1,465 @@
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+use crate::board::{Bitboard, Move8, Pos};
+use crate::cancel::CancelToken;
+use crate::eval::{evaluate, Score};
+use crate::movegen::{MoveList, MoveOrder};
+use crate::ordering::MoveTable;
+use crate::symmetry::inverse;
+use crate::tt::{Bound, Entry, TranspositionTable};
+
+/// Score of a won game at the root; wins further away score lower. Fits the
+/// `i16` scores of the transposition table.
+pub const WIN_SCORE: Score = 30_000;
+/// No game lasts longer than 81 plies.
+pub const MAX_PLY: usize = 81;
+/// Scores beyond this magnitude are proven wins or losses.
+pub const WIN_THRESHOLD: Score = WIN_SCORE - MAX_PLY as Score - 1;
+
+pub fn is_win_score(score: Score) -> bool {
+    score.abs() > WIN_THRESHOLD
+}
+
+// win scores are stored relative to the node, not the root
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    match score {
+        s if s > WIN_THRESHOLD => s + ply as Score,
+        s if s < -WIN_THRESHOLD => s - ply as Score,
+        s => s,
+    }
+}
+
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    match score {
+        s if s > WIN_THRESHOLD => s - ply as Score,
+        s if s < -WIN_THRESHOLD => s + ply as Score,
+        s => s,
+    }
+}
+
+// how a fail-soft score relates to the true value given the search window
+fn bound(score: Score, alpha: Score, beta: Score) -> Bound {
+    if score >= beta {
+        Bound::Lower
+    } else if score > alpha {
+        Bound::Exact
+    } else {
+        Bound::Upper
+    }
+}
+
+fn terminal_score(board: &Bitboard, ply: usize) -> Score {
+    match board.winner() {
+        Some(p) if p == board.side_to_move() => WIN_SCORE - ply as Score,
+        Some(_) => -(WIN_SCORE - ply as Score),
+        None => 0,
+    }
+}
+
+/// When to stop an iterative-deepening search; `None` means unlimited.
+///
+/// At least depth 1 is always completed so that a move is available.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> SearchLimits {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Best root move, `None` if the game is already over.
+    pub best: Option<Pos>,
+    /// Score from the side to move's point of view.
+    pub score: Score,
+    /// Whether `score` is exact or only a bound on the true value.
+    pub bound: Bound,
+    /// Depth of the last completed iteration.
+    pub depth: u8,
+    /// Nodes searched over all iterations, including an aborted one.
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Transposition table lookups and how many found an entry.
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    /// Principal variation, starting with `best`.
+    pub pv: Vec<Pos>,
+}
+
+/// FNV-1a hash over the depth, best move, score and node count of every
+/// iteration fed to it, in order. Stable across builds and platforms, so a
+/// changed value means the search itself behaves differently.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Signature(u64);
+
+impl Default for Signature {
+    fn default() -> Self {
+        Signature(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Signature {
+    pub fn update(&mut self, result: &SearchResult) {
+        let best = Move8::from(result.best).to_u8();
+        let (score, nodes) = (result.score.to_le_bytes(), result.nodes.to_le_bytes());
+        for &byte in [result.depth, best].iter().chain(&score).chain(&nodes) {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+}
+
+/// Iterative-deepening fail-soft negamax alpha-beta search backed by a
+/// transposition table.
+pub struct Searcher {
+    tt: TranspositionTable,
+    nodes: u64,
+    tt_probes: u64,
+    tt_hits: u64,
+    limits: SearchLimits,
+    start: Instant,
+    aborted: bool,
+    cancel: CancelToken,
+    // best move of the previous iteration, searched first at the root
+    root_hint: Option<Pos>,
+    symmetric_tt: bool,
+    move_table: MoveTable,
+    move_order: MoveOrder,
+}
+
+impl Searcher {
+    pub fn new(tt_mb: usize) -> Searcher {
+        Searcher {
+            tt: TranspositionTable::new(tt_mb),
+            nodes: 0,
+            tt_probes: 0,
+            tt_hits: 0,
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            aborted: false,
+            cancel: CancelToken::default(),
+            root_hint: None,
+            symmetric_tt: false,
+            move_table: MoveTable::default(),
+            move_order: MoveOrder::Natural,
+        }
+    }
+
+    /// Token checked during every later search; once it fires, searches
+    /// stop as soon as depth 1 is complete.
+    pub fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    /// Forgets the previous game. With `keep_tt` its table entries survive,
+    /// aged so that the new game replaces them first; otherwise they are
+    /// cleared.
+    pub fn new_game(&mut self, keep_tt: bool) {
+        if keep_tt {
+            self.tt.new_search();
+        } else {
+            self.tt.clear();
+        }
+        self.root_hint = None;
+    }
+
+    /// Keys the table by [`Bitboard::canonical_hash`], so that positions
+    /// symmetric to each other share one entry, with the best move stored
+    /// as played in the canonical position. Saves a lot of memory when
+    /// solving from early positions, at the cost of canonicalizing every
+    /// node; off by default. Clear the table when switching.
+    pub fn set_symmetric_tt(&mut self, enabled: bool) {
+        self.symmetric_tt = enabled;
+    }
+
+    /// Orders moves that neither the table nor the previous iteration
+    /// suggest by `table`, highest priority first; the default weights
+    /// favour center fields and squares.
+    pub fn set_move_table(&mut self, table: MoveTable) {
+        self.move_table = table;
+    }
+
+    /// Generates moves in `order` before they are sorted, which decides
+    /// between moves of equal priority in the move table; natural by default.
+    pub fn set_move_order(&mut self, order: MoveOrder) {
+        self.move_order = order;
+    }
+
+    // table key of `board` and the symmetry mapping it to the position the
+    // key stands for
+    fn tt_key(&self, board: &Bitboard) -> (u64, usize) {
+        if self.symmetric_tt {
+            let sym = board.canonical_symmetry();
+            (board.transformed(sym).hash(), sym)
+        } else {
+            (board.hash(), 0)
+        }
+    }
+
+    fn probe(&self, board: &Bitboard) -> (u64, usize, Option<Entry>) {
+        let (key, sym) = self.tt_key(board);
+        let entry = self.tt.probe(key).map(|mut entry| {
+            if sym != 0 {
+                entry.best = entry
+                    .best
+                    .pos()
+                    .map(|pos| pos.transformed(inverse(sym)))
+                    .into();
+            }
+            entry
+        });
+        (key, sym, entry)
+    }
+
+    pub fn tt(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    pub fn tt_mut(&mut self) -> &mut TranspositionTable {
+        &mut self.tt
+    }
+
+    /// Searches to exactly `depth` plies (at least 1).
+    pub fn search(&mut self, board: &Bitboard, depth: u8) -> SearchResult {
+        self.think(board, SearchLimits::depth(depth))
+    }
+
+    pub fn think(&mut self, board: &Bitboard, limits: SearchLimits) -> SearchResult {
+        self.think_with(board, limits, |_| {})
+    }
+
+    /// Deepens one ply at a time until a limit is hit, calling `on_iteration`
+    /// after every completed iteration; an aborted iteration is discarded.
+    pub fn think_with<F: FnMut(&SearchResult)>(
+        &mut self,
+        board: &Bitboard,
+        limits: SearchLimits,
+        on_iteration: F,
+    ) -> SearchResult {
+        self.tt.new_search();
+        self.deepen(board, limits, on_iteration)
+    }
+
+    /// Like [`Searcher::search`], but as part of the previous search: the
+    /// table keeps its age, so several searches of one turn (such as those
+    /// of a [`RefutationTable`]) do not age each other's entries out.
+    ///
+    /// [`RefutationTable`]: crate::refutation::RefutationTable
+    pub fn search_continued(&mut self, board: &Bitboard, depth: u8) -> SearchResult {
+        self.deepen(board, SearchLimits::depth(depth), |_| {})
+    }
+
+    fn deepen<F: FnMut(&SearchResult)>(
+        &mut self,
+        board: &Bitboard,
+        limits: SearchLimits,
+        mut on_iteration: F,
+    ) -> SearchResult {
+        let mut board = *board;
+        self.nodes = 0;
+        self.tt_probes = 0;
+        self.tt_hits = 0;
+        self.limits = limits;
+        self.start = Instant::now();
+        self.aborted = false;
+        self.root_hint = None;
+        let max_depth = limits
+            .depth
+            .unwrap_or(MAX_PLY as u8)
+            .clamp(1, MAX_PLY as u8);
+        let mut result = SearchResult {
+            best: None,
+            score: 0,
+            bound: Bound::Exact,
+            depth: 0,
+            nodes: 0,
+            elapsed: Duration::default(),
+            tt_probes: 0,
+            tt_hits: 0,
+            pv: Vec::new(),
+        };
+        for depth in 1..=max_depth {
+            let (alpha, beta) = (-WIN_SCORE, WIN_SCORE);
+            let score = self.alphabeta(&mut board, depth, 0, alpha, beta);
+            if self.aborted {
+                break;
+            }
+            let pv = self.pv(&board, depth as usize);
+            self.root_hint = pv.first().copied();
+            result = SearchResult {
+                best: self.root_hint,
+                score,
+                bound: bound(score, alpha, beta),
+                depth,
+                nodes: self.nodes,
+                elapsed: self.start.elapsed(),
+                tt_probes: self.tt_probes,
+                tt_hits: self.tt_hits,
+                pv,
+            };
+            on_iteration(&result);
+            if result.best.is_none() || is_win_score(score) || self.out_of_time(2) {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result.tt_probes = self.tt_probes;
+        result.tt_hits = self.tt_hits;
+        result.elapsed = self.start.elapsed();
+        result
+    }
+
+    // true once `1 / fraction` of the move time is used up
+    fn out_of_time(&self, fraction: u32) -> bool {
+        self.limits
+            .movetime
+            .is_some_and(|t| self.start.elapsed() >= t / fraction)
+    }
+
+    fn should_abort(&mut self) -> bool {
+        // depth 1 always completes so that there is a move to play
+        if self.aborted || self.root_hint.is_none() {
+            return self.aborted;
+        }
+        if self.limits.nodes.is_some_and(|n| self.nodes >= n)
+            || (self.nodes & 1023 == 0 && (self.out_of_time(1) || self.cancel.is_cancelled()))
+        {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    fn alphabeta(
+        &mut self,
+        board: &mut Bitboard,
+        depth: u8,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+    ) -> Score {
+        self.nodes += 1;
+        if self.should_abort() {
+            return 0;
+        }
+        if board.game_over() {
+            return terminal_score(board, ply);
+        }
+        if depth == 0 {
+            return evaluate(board);
+        }
+        let (key, sym, entry) = self.probe(board);
+        let mut tt_move = None;
+        self.tt_probes += 1;
+        if let Some(entry) = entry {
+            self.tt_hits += 1;
+            tt_move = entry.best.pos();
+            if entry.depth >= depth && ply > 0 {
+                let score = score_from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut moves = MoveList::new();
+        board.legal_moves_ordered(self.move_order, &mut moves);
+        moves.sort_by_key(|mov| Reverse(self.move_table.priority(mov.pos())));
+        let first = if ply == 0 {
+            self.root_hint.or(tt_move)
+        } else {
+            tt_move
+        };
+        if let Some(i) = moves.iter().position(|mov| Some(mov.pos()) == first) {
+            moves[..=i].rotate_right(1);
+        }
+
+        let alpha_orig = alpha;
+        let mut best_score = -WIN_SCORE;
+        let mut best = None;
+        for mov in moves.iter() {
+            #[cfg(feature = "check-undo")]
+            let snapshot = *board;
+            // SAFETY: `mov` was generated for this position
+            unsafe { board.make_move_unchecked(mov.pos()) };
+            let score = -self.alphabeta(board, depth - 1, ply + 1, -beta, -alpha);
+            unsafe { board.undo_move_unchecked(mov) };
+            #[cfg(feature = "check-undo")]
+            snapshot.assert_restored(board, mov.pos());
+            if self.aborted {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best = Some(mov.pos());
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let best = best.or(tt_move);
+        self.tt.store(Entry {
+            key,
+            depth,
+            bound: bound(best_score, alpha_orig, beta),
+            score: score_to_tt(best_score, ply),
+            best: if sym != 0 {
+                best.map(|pos| pos.transformed(sym)).into()
+            } else {
+                best.into()
+            },
+        });
+        best_score
+    }
+
+    /// Principal variation read back from the transposition table.
+    pub fn pv(&self, board: &Bitboard, max_len: usize) -> Vec<Pos> {
+        let mut board = *board;
+        let mut pv = Vec::with_capacity(max_len.min(MAX_PLY));
+        while pv.len() < max_len && !board.game_over() {
+            let pos = match self.probe(&board).2.and_then(|e| e.best.pos()) {
+                Some(pos) => pos,
+                None => break,
+            };
+            if !board.is_legal(pos) {
+                break;
+            }
+            board.make_move(pos);
+            pv.push(pos);
+        }
+        pv
+    }
+}
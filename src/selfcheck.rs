@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::board::{Bitboard, GameResult, MoveError, Player, Pos};
+use crate::eval::{evaluate, Score};
+use crate::generic::GenericBoard;
+use crate::mcts::{MctsConfig, MctsEngine};
+use crate::notation::parse_moves;
+use crate::perft::{perft, STARTPOS_PERFT};
+use crate::protocol::Engine;
+use crate::rng::Rng;
+use crate::rules::{Rules, TieBreak, WonFields};
+use crate::search::WIN_SCORE;
+use crate::symmetry::SYMMETRIES;
+use crate::tt::{Bound, Entry, TranspositionTable};
+use crate::verify::{verify_movegen, verify_parsers};
+
+/// Outcome of a single self-check.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    pub millis: u128,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = if self.passed { "pass" } else { "FAIL" };
+        write!(
+            f,
+            "[{}] {:<10} {} ({}ms)",
+            status, self.name, self.detail, self.millis
+        )
+    }
+}
+
+fn check<F: FnOnce() -> Result<String, String>>(name: &'static str, func: F) -> Check {
+    let time = Instant::now();
+    let (passed, detail) = match func() {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    Check {
+        name,
+        passed,
+        detail,
+        millis: time.elapsed().as_millis(),
+    }
+}
+
+fn check_perft(max_depth: usize) -> Result<String, String> {
+    for (depth, &expected) in STARTPOS_PERFT.iter().enumerate().take(max_depth + 1) {
+        let actual = perft(&mut Bitboard::new(), depth);
+        if actual != expected {
+            return Err(format!(
+                "perft({}) = {}, expected {}",
+                depth, actual, expected
+            ));
+        }
+    }
+    Ok(format!("depths 0..={} match reference counts", max_depth))
+}
+
+fn check_mcts(iterations: usize) -> Result<String, String> {
+    let board = Bitboard::new();
+    let result = MctsEngine::new(MctsConfig::default()).search(&board, iterations);
+    let visits: u32 = result.children.iter().map(|c| c.visits).sum();
+    match result.best {
+        Some(pos) if board.is_legal(pos) => {}
+        other => return Err(format!("illegal best move {:?}", other)),
+    }
+    if visits as usize != iterations {
+        return Err(format!(
+            "root children visited {} times, expected {}",
+            visits, iterations
+        ));
+    }
+    Ok(format!("{} iterations, legal best move", iterations))
+}
+
+// random positions with the given number of plies played
+fn random_positions(count: usize, seed: u64) -> Vec<Bitboard> {
+    (0..count)
+        .map(|i| {
+            let mut rng = Rng::for_worker(seed, i);
+            let mut board = Bitboard::new();
+            for _ in 0..rng.below(60) {
+                let moves: Vec<_> = board.moves().collect();
+                match rng.choose(&moves) {
+                    Some(&pos) => board.make_move(pos),
+                    None => break,
+                };
+            }
+            board
+        })
+        .collect()
+}
+
+fn check_eval(positions: usize) -> Result<String, String> {
+    for board in random_positions(positions, 0) {
+        let score = evaluate(&board);
+        for sym in 1..SYMMETRIES {
+            let image = board.transformed(sym);
+            if evaluate(&image) != score {
+                return Err(format!(
+                    "eval {} but {} after symmetry {}:\n{}",
+                    score,
+                    evaluate(&image),
+                    sym,
+                    board
+                ));
+            }
+        }
+    }
+    Ok(format!(
+        "{} positions, same under all 8 symmetries",
+        positions
+    ))
+}
+
+fn check_tt(entries: usize) -> Result<String, String> {
+    let mut tt = TranspositionTable::new(1);
+    let mut rng = Rng::new(0);
+    let bounds = [Bound::Exact, Bound::Lower, Bound::Upper];
+    for _ in 0..entries {
+        let entry = Entry {
+            key: rng.next_u64(),
+            depth: rng.below(256) as u8,
+            bound: bounds[rng.below(3)],
+            score: rng.below(2 * WIN_SCORE as usize + 1) as Score - WIN_SCORE,
+            best: Pos::from_index(rng.below(81) as u8).into(),
+        };
+        tt.store(entry);
+        match tt.probe(entry.key) {
+            Some(probed) if probed == entry => {}
+            probed => return Err(format!("stored {:?}, probed {:?}", entry, probed)),
+        }
+    }
+    Ok(format!("{} entries stored and probed back", entries))
+}
+
+fn check_protocol() -> Result<String, String> {
+    let out = Arc::new(Mutex::new(Vec::new()));
+    let mut engine = Engine::with_output(out.clone());
+    for line in [
+        "uci",
+        "isready",
+        "position startpos moves 5-5 5-1",
+        "go depth 3",
+    ] {
+        engine.handle(line);
+    }
+    engine.wait();
+    let out = out.lock().map_err(|_| "output poisoned")?;
+    let out = String::from_utf8_lossy(&out);
+    let board = parse_moves(["5-5", "5-1"]).map_err(|err| err.to_string())?;
+    let best = out.lines().find_map(|l| l.strip_prefix("bestmove "));
+    match best.map(|m| board.parse_move(m)) {
+        Some(Ok(_)) if out.contains("uciok") && out.contains("readyok") => {}
+        _ => return Err(format!("unexpected replies:\n{}", out)),
+    }
+    Ok("uci, isready, position and go answered".to_string())
+}
+
+// `GenericBoard<3>` and `Bitboard` play random games side by side
+fn check_generic(games: usize, seed: u64) -> Result<String, String> {
+    let mut plies = 0;
+    for game in 0..games {
+        let mut rng = Rng::for_worker(seed, game);
+        let mut board = Bitboard::new();
+        let mut generic = GenericBoard::<3>::new();
+        loop {
+            let moves: Vec<Pos> = board.moves().collect();
+            let generic_moves: Vec<(u8, u8)> =
+                generic.moves().map(|p| (p.field(), p.square())).collect();
+            let same_fields = (0..9).all(|f| board.field_status(f) == generic.field_status(f));
+            let bitboard_moves: Vec<(u8, u8)> =
+                moves.iter().map(|p| (p.field(), p.square())).collect();
+            if bitboard_moves != generic_moves
+                || !same_fields
+                || board.forced_field() != generic.forced_field()
+                || board.result() != generic.result()
+            {
+                return Err(format!(
+                    "generic board differs after {} plies:\n{}",
+                    board.ply(),
+                    board
+                ));
+            }
+            let pos = match rng.choose(&moves) {
+                Some(&pos) => pos,
+                None => break,
+            };
+            board.make_move(pos);
+            let generic_pos = GenericBoard::<3>::pos(pos.field(), pos.square())
+                .expect("bitboard moves are on the board");
+            generic.make_move(generic_pos);
+            plies += 1;
+        }
+    }
+    let outside = GenericBoard::<5>::pos(24, 24).expect("on a 5x5 board");
+    match GenericBoard::<3>::new().check_move(outside) {
+        Err(MoveError::OutOfRange) => {}
+        other => return Err(format!("move {} off the board: {:?}", outside, other)),
+    }
+    Ok(format!(
+        "{} random games ({} plies) match the bitboard",
+        games, plies
+    ))
+}
+
+fn result_bit(result: GameResult) -> u8 {
+    match result {
+        GameResult::Win(Player::X) => 1,
+        GameResult::Win(Player::O) => 2,
+        GameResult::Tie => 4,
+    }
+}
+
+// every result reachable from `board` by any play, as `result_bit`s; `None`
+// if finding out takes more than `budget` nodes
+fn reachable(board: &mut Bitboard, seen: &mut HashMap<u64, u8>, budget: &mut usize) -> Option<u8> {
+    if let Some(result) = board.result() {
+        return Some(result_bit(result));
+    }
+    if let Some(&results) = seen.get(&board.hash()) {
+        return Some(results);
+    }
+    *budget = budget.checked_sub(1)?;
+    let mut results = 0;
+    for pos in board.moves().collect::<Vec<_>>() {
+        let mov = board.make_move(pos);
+        let below = reachable(board, seen, budget);
+        board.undo_move(&mov);
+        results |= below?;
+    }
+    seen.insert(board.hash(), results);
+    Some(results)
+}
+
+// solves positions of random games that `decided_result` calls decided
+fn check_decided(games: usize, seed: u64) -> Result<String, String> {
+    let tie_break = Rules {
+        tie_break: TieBreak::FieldsWon,
+        ..Rules::default()
+    };
+    let playable = Rules {
+        won_fields: WonFields::Playable,
+        ..tie_break
+    };
+    let mut solved = 0;
+    for (i, &rules) in [Rules::default(), tie_break, playable].iter().enumerate() {
+        for game in 0..games {
+            let mut rng = Rng::for_worker(seed, i * games + game);
+            let mut board = Bitboard::with_rules(rules);
+            let mut seen = HashMap::new();
+            while !board.game_over() {
+                if let Some(expected) = board.decided_result() {
+                    let mut budget = 100_000;
+                    match reachable(&mut board, &mut seen, &mut budget) {
+                        Some(results) if results != result_bit(expected) => {
+                            return Err(format!(
+                                "{:?}: decided as {} but other results are reachable:\n{}",
+                                rules, expected, board
+                            ))
+                        }
+                        Some(_) => solved += 1,
+                        None => {}
+                    }
+                }
+                let moves: Vec<_> = board.moves().collect();
+                board.make_move(*rng.choose(&moves).expect("the game is on"));
+            }
+        }
+    }
+    Ok(format!(
+        "{} decided positions solved under 3 rule sets",
+        solved
+    ))
+}
+
+/// Runs a quick battery of consistency checks over the public APIs.
+pub fn selfcheck() -> Vec<Check> {
+    vec![
+        check("perft", || check_perft(6)),
+        check("movegen", || {
+            verify_movegen(200, 0)
+                .map(|plies| format!("200 random games ({} plies), make/undo and hash", plies))
+                .map_err(|d| d.to_string())
+        }),
+        check("parsers", || {
+            verify_parsers(10_000, 0)
+                .map(|n| format!("{} parsers on 10000 malformed inputs", n))
+                .map_err(|p| p.to_string())
+        }),
+        check("mcts", || check_mcts(2000)),
+        check("eval", || check_eval(500)),
+        check("tt", || check_tt(10_000)),
+        check("protocol", check_protocol),
+        check("decided", || check_decided(500, 0)),
+        check("generic", || check_generic(200, 0)),
+    ]
+}
@@ -0,0 +1,83 @@
+//! The 8 symmetries of the board (rotations and reflections, applied to the
+//! meta board and to every local field alike) and canonical positions.
+//!
+//! Symmetry `sym` rotates `sym % 4` quarter turns clockwise, then mirrors
+//! left to right if `sym >= 4`; 0 is the identity.
+
+use crate::board::{Bitboard, Bits, Index, Pos};
+use crate::pattern::{transform, transform_square};
+
+pub const SYMMETRIES: usize = 8;
+
+/// The symmetry that undoes `sym`.
+pub fn inverse(sym: usize) -> usize {
+    match sym {
+        0..=3 => (4 - sym) % 4,
+        // reflections are their own inverse
+        _ => sym,
+    }
+}
+
+fn transform_index(index: Index, sym: usize) -> Index {
+    transform_square(index as usize, sym) as Index
+}
+
+impl Pos {
+    pub fn transformed(self, sym: usize) -> Pos {
+        Pos {
+            field: transform_index(self.field, sym),
+            square: 1 << transform_index(self.square(), sym),
+        }
+    }
+}
+
+impl Bitboard {
+    /// The position mapped by symmetry `sym`, with the same side to move
+    /// and rules.
+    pub fn transformed(&self, sym: usize) -> Bitboard {
+        let mut board = *self;
+        for field in 0..9 {
+            let to = transform_index(field, sym) as usize;
+            for p in 0..2 {
+                board.board[p][to] = transform(self.board[p][field as usize], sym);
+            }
+            board.field_status[to] = self.field_status[field as usize];
+        }
+        for p in 0..2 {
+            board.meta_field[p] = transform(self.meta_field[p], sym);
+        }
+        board.valid_field = self.valid_field.map(|field| transform_index(field, sym));
+        if self.last_move().is_some() {
+            board.last_move = self.last_move.transformed(sym);
+        }
+        board.hash = board.compute_hash();
+        board
+    }
+
+    // the order in which `canonical` picks the smallest transform; the last
+    // move only settles ties between transforms of a symmetric position
+    fn canonical_key(&self) -> ([[Bits; 9]; 2], Option<Index>, (Index, Bits)) {
+        let last = (self.last_move.field, self.last_move.square);
+        (self.board, self.valid_field, last)
+    }
+
+    /// The symmetry that maps this position to [`Bitboard::canonical`]; map
+    /// moves found for the canonical position back with [`inverse`].
+    pub fn canonical_symmetry(&self) -> usize {
+        (0..SYMMETRIES)
+            .min_by_key(|&sym| self.transformed(sym).canonical_key())
+            .expect("there are 8 symmetries")
+    }
+
+    /// The lexicographically smallest of the 8 transforms of the position,
+    /// comparing stones, then the forced field and the last move; the same
+    /// for all positions that are symmetric to each other.
+    pub fn canonical(&self) -> Bitboard {
+        self.transformed(self.canonical_symmetry())
+    }
+
+    /// [`Bitboard::hash`] of the canonical position.
+    pub fn canonical_hash(&self) -> u64 {
+        self.canonical().hash()
+    }
+}
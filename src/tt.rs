@@ -0,0 +1,227 @@
+use crate::board::Move8;
+use crate::eval::Score;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The score is at least the stored value (fail high).
+    Lower,
+    /// The score is at most the stored value (fail low).
+    Upper,
+}
+
+/// An unpacked table entry; scores must fit in an `i16`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: u8,
+    pub bound: Bound,
+    pub score: Score,
+    pub best: Move8,
+}
+
+// Entry packed into 8 bytes, zero when empty:
+//   bits  0..24  top 24 bits of the key (the low bits select the bucket)
+//   bits 24..32  best move as a Move8
+//   bits 32..48  score as i16
+//   bits 48..56  depth
+//   bits 56..58  bound, 1..=3
+//   bits 58..64  age of the search that stored it
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+struct Packed(u64);
+
+const AGE_MASK: u8 = 0x3f;
+
+impl Packed {
+    fn pack(entry: &Entry, age: u8) -> Packed {
+        debug_assert!(entry.score.abs() <= i16::MAX as Score);
+        let bound = match entry.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        Packed(
+            (entry.key >> 40)
+                | (entry.best.to_u8() as u64) << 24
+                | (entry.score as i16 as u16 as u64) << 32
+                | (entry.depth as u64) << 48
+                | (bound as u64) << 56
+                | (age as u64) << 58,
+        )
+    }
+
+    fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    fn matches(self, key: u64) -> bool {
+        !self.is_empty() && self.0 & 0xff_ffff == key >> 40
+    }
+
+    fn depth(self) -> u8 {
+        (self.0 >> 48) as u8
+    }
+
+    fn bound(self) -> Bound {
+        match (self.0 >> 56) & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+
+    fn age(self) -> u8 {
+        (self.0 >> 58) as u8
+    }
+
+    fn unpack(self, key: u64) -> Entry {
+        Entry {
+            key,
+            depth: self.depth(),
+            bound: self.bound(),
+            score: (self.0 >> 32) as u16 as i16 as Score,
+            best: Move8::from_u8((self.0 >> 24) as u8).unwrap_or(Move8::NONE),
+        }
+    }
+}
+
+const BUCKET_SIZE: usize = 8;
+
+// one bucket per cache line
+#[repr(align(64))]
+#[derive(Copy, Clone, Default)]
+struct Bucket([Packed; BUCKET_SIZE]);
+
+/// Fixed-size transposition table of 8-entry, cache-line sized buckets.
+///
+/// Within a bucket an entry for the same key is overwritten unless that would
+/// replace an exact score with a shallower bound; otherwise an empty slot, or
+/// the one with the least depth after penalizing entries from older searches,
+/// is replaced.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: u8,
+}
+
+impl TranspositionTable {
+    /// Table using at most `mb` megabytes, rounded down to a power-of-two bucket count.
+    pub fn new(mb: usize) -> TranspositionTable {
+        let bytes = mb.max(1) << 20;
+        let n = (bytes / std::mem::size_of::<Bucket>()).max(1);
+        let n = 1 << (usize::BITS - 1 - n.leading_zeros());
+        TranspositionTable {
+            buckets: vec![Bucket::default(); n],
+            age: 0,
+        }
+    }
+
+    pub fn size_mb(&self) -> usize {
+        (self.buckets.len() * std::mem::size_of::<Bucket>()) >> 20
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            *bucket = Bucket::default();
+        }
+        self.age = 0;
+    }
+
+    /// Marks entries stored so far as older than the ones to come, making
+    /// them preferred for replacement.
+    pub fn new_search(&mut self) {
+        self.age = (self.age + 1) & AGE_MASK;
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        key as usize & (self.buckets.len() - 1)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.buckets[self.bucket(key)]
+            .0
+            .iter()
+            .find(|e| e.matches(key))
+            .map(|e| e.unpack(key))
+    }
+
+    pub fn store(&mut self, entry: Entry) {
+        let (index, age) = (self.bucket(entry.key), self.age);
+        let bucket = &mut self.buckets[index].0;
+        if let Some(slot) = bucket.iter_mut().find(|e| e.matches(entry.key)) {
+            let old = slot.unpack(entry.key);
+            if entry.bound == Bound::Exact || old.bound != Bound::Exact || entry.depth >= old.depth
+            {
+                let best = entry.best.or(old.best);
+                *slot = Packed::pack(&Entry { best, ..entry }, age);
+            }
+            return;
+        }
+        let slot = bucket
+            .iter_mut()
+            .min_by_key(|e| {
+                if e.is_empty() {
+                    return i32::MIN;
+                }
+                let stale = (age.wrapping_sub(e.age()) & AGE_MASK) as i32;
+                e.depth() as i32 - 8 * stale
+            })
+            .expect("buckets are non-empty");
+        *slot = Packed::pack(&entry, age);
+    }
+
+    /// Entries from the current search per thousand, sampled from the first buckets.
+    pub fn hashfull(&self) -> usize {
+        let sample = &self.buckets[..self.buckets.len().min(125)];
+        let used: usize = sample
+            .iter()
+            .map(|b| {
+                b.0.iter()
+                    .filter(|e| !e.is_empty() && e.age() == self.age)
+                    .count()
+            })
+            .sum();
+        used * 1000 / (sample.len() * BUCKET_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Pos;
+
+    #[test]
+    fn packed_round_trip_at_the_extremes() {
+        let moves = [
+            Move8::NONE,
+            Pos::from_index(0).expect("on the board").into(),
+            Pos::from_index(80).expect("on the board").into(),
+        ];
+        for &key in &[0, u64::MAX] {
+            for &score in &[-(i16::MAX as Score), 0, i16::MAX as Score] {
+                for &depth in &[0, u8::MAX] {
+                    for &bound in &[Bound::Exact, Bound::Lower, Bound::Upper] {
+                        for &age in &[0, AGE_MASK] {
+                            for &best in &moves {
+                                let entry = Entry {
+                                    key,
+                                    depth,
+                                    bound,
+                                    score,
+                                    best,
+                                };
+                                let packed = Packed::pack(&entry, age);
+                                assert!(packed.matches(key), "{:?}", entry);
+                                assert_eq!(packed.unpack(key), entry);
+                                assert_eq!(packed.age(), age, "{:?}", entry);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
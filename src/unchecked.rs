@@ -0,0 +1,30 @@
+//! Unvalidated make/undo for engines that only play moves they generated.
+
+use crate::board::{Bitboard, Move, Pos};
+
+pub trait UncheckedMoves {
+    /// [`Bitboard::make_move`] without the legality check.
+    ///
+    /// # Safety
+    ///
+    /// `pos` must be a legal move in the current position.
+    unsafe fn make_move_unchecked(&mut self, pos: Pos);
+
+    /// [`Bitboard::undo_move`] without the occupancy check.
+    ///
+    /// # Safety
+    ///
+    /// `mov` must be the last move played on this board, as generated for
+    /// the position before it.
+    unsafe fn undo_move_unchecked(&mut self, mov: &Move);
+}
+
+impl UncheckedMoves for Bitboard {
+    unsafe fn make_move_unchecked(&mut self, pos: Pos) {
+        Bitboard::make_move_unchecked(self, pos)
+    }
+
+    unsafe fn undo_move_unchecked(&mut self, mov: &Move) {
+        Bitboard::undo_move_unchecked(self, mov)
+    }
+}
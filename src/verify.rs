@@ -0,0 +1,527 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io;
+use std::panic;
+use std::sync::{Arc, Mutex};
+
+use crate::board::{Bitboard, FieldStatus, GameResult, Index, Move, Player, Pos};
+use crate::book::{self, Book};
+use crate::features::parse_game_line;
+use crate::movegen::{MoveList, MoveOrder};
+use crate::notation::parse_moves;
+use crate::opening::Openings;
+use crate::pattern::Pattern;
+use crate::protocol::Engine;
+use crate::rng::Rng;
+use crate::rules::{Rules, TieBreak, WonFields};
+
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2],
+    [3, 4, 5],
+    [6, 7, 8],
+    [0, 3, 6],
+    [1, 4, 7],
+    [2, 5, 8],
+    [0, 4, 8],
+    [2, 4, 6],
+];
+
+// (field, square) with both in 0..9
+type Cell = (usize, usize);
+
+// rule sets the random games of `verify_movegen` take turns with
+const RULE_SETS: [Rules; 3] = [
+    Rules {
+        tie_break: TieBreak::None,
+        won_fields: WonFields::Closed,
+    },
+    Rules {
+        tie_break: TieBreak::FieldsWon,
+        won_fields: WonFields::Closed,
+    },
+    Rules {
+        tie_break: TieBreak::FieldsWon,
+        won_fields: WonFields::Playable,
+    },
+];
+
+/// Straightforward array-based implementation of the rules, used as ground truth.
+#[derive(Clone, Default)]
+pub struct Reference {
+    cells: [[Option<Player>; 9]; 9],
+    status: [FieldStatus; 9],
+    forced: Option<usize>,
+    turn: Player,
+    over: bool,
+    rules: Rules,
+}
+
+impl Reference {
+    pub fn with_rules(rules: Rules) -> Reference {
+        Reference {
+            rules,
+            ..Reference::default()
+        }
+    }
+
+    fn full(&self, field: usize) -> bool {
+        self.cells[field].iter().all(Option::is_some)
+    }
+
+    // whether a field takes no more stones
+    fn closed(&self, field: usize) -> bool {
+        match self.rules.won_fields {
+            WonFields::Closed => self.status[field].blocked(),
+            WonFields::Playable => self.full(field),
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Cell> {
+        let mut moves = Vec::new();
+        if self.over {
+            return moves;
+        }
+        for field in 0..9 {
+            if self.forced.is_some_and(|f| f != field) || self.closed(field) {
+                continue;
+            }
+            for square in 0..9 {
+                if self.cells[field][square].is_none() {
+                    moves.push((field, square));
+                }
+            }
+        }
+        moves
+    }
+
+    pub fn play(&mut self, (field, square): Cell) {
+        let p = self.turn;
+        self.cells[field][square] = Some(p);
+        let cells = &self.cells[field];
+        // a field counts for its first winner only
+        if !self.status[field].blocked() {
+            if LINES.iter().any(|l| l.iter().all(|&s| cells[s] == Some(p))) {
+                self.status[field] = FieldStatus::won_by(p);
+            } else if cells.iter().all(Option::is_some) {
+                self.status[field] = FieldStatus::Tied;
+            }
+        }
+        let status = &self.status;
+        if LINES.iter().any(|l| l.iter().all(|&f| status[f].won(p)))
+            || (0..9).all(|f| self.closed(f))
+        {
+            self.over = true;
+        }
+        self.forced = if self.closed(square) {
+            None
+        } else {
+            Some(square)
+        };
+        self.turn = p.other();
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.over {
+            return None;
+        }
+        let won = |p: Player| self.status.iter().filter(|s| s.won(p)).count();
+        let line = Player::ALL.iter().copied().find(|&p| {
+            LINES
+                .iter()
+                .any(|l| l.iter().all(|&f| self.status[f].won(p)))
+        });
+        let winner = match (line, self.rules.tie_break) {
+            (Some(p), _) => Some(p),
+            (None, TieBreak::None) => None,
+            (None, TieBreak::FieldsWon) => match won(Player::X).cmp(&won(Player::O)) {
+                Ordering::Greater => Some(Player::X),
+                Ordering::Less => Some(Player::O),
+                Ordering::Equal => None,
+            },
+        };
+        Some(winner.map_or(GameResult::Tie, GameResult::Win))
+    }
+}
+
+enum Outcome {
+    Consistent,
+    Illegal,
+    // the divergence is reproduced by the first `ply` moves of the sequence
+    Diverged { ply: usize, reason: String },
+}
+
+fn cell(mov: &Move) -> Cell {
+    let pos = mov.pos();
+    (pos.field() as usize, pos.square() as usize)
+}
+
+// in move notation, like `Pos` displays
+fn format_moves(moves: &[Cell]) -> String {
+    let moves: Vec<_> = moves
+        .iter()
+        .map(|(f, s)| format!("{}-{}", f + 1, s + 1))
+        .collect();
+    moves.join(" ")
+}
+
+/// First disagreement between [`Bitboard`] and the reference found by [`verify_movegen`].
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub game: usize,
+    pub seed: u64,
+    /// Rules the game was played under.
+    pub rules: Rules,
+    /// Minimized move sequence reproducing the divergence from the starting position.
+    pub moves: Vec<Pos>,
+    pub reason: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves: Vec<String> = self.moves.iter().map(|pos| pos.to_string()).collect();
+        writeln!(
+            f,
+            "divergence in game {} (seed {}, {:?})",
+            self.game + 1,
+            self.seed,
+            self.rules
+        )?;
+        writeln!(f, "moves: {}", moves.join(" "))?;
+        write!(f, "{}", self.reason)
+    }
+}
+
+/// An input that made a parser panic, found by [`verify_parsers`].
+#[derive(Clone, Debug)]
+pub struct ParserPanic {
+    pub parser: &'static str,
+    pub case: usize,
+    pub seed: u64,
+    pub input: String,
+}
+
+impl fmt::Display for ParserPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} parser panicked on case {} (seed {})",
+            self.parser,
+            self.case + 1,
+            self.seed
+        )?;
+        write!(f, "input: {:?}", self.input)
+    }
+}
+
+fn generated_moves(board: &mut Bitboard, order: MoveOrder) -> Vec<Move> {
+    let mut moves = MoveList::new();
+    board.legal_moves_ordered(order, &mut moves);
+    moves.to_vec()
+}
+
+fn compare(board: &mut Bitboard, reference: &Reference) -> Option<String> {
+    if board.game_over() != reference.over {
+        return Some(format!(
+            "game over: bitboard={}, reference={}",
+            board.game_over(),
+            reference.over
+        ));
+    }
+    let ply = reference
+        .cells
+        .iter()
+        .flatten()
+        .filter(|c| c.is_some())
+        .count();
+    let blocked = reference.status.iter().filter(|s| s.blocked()).count();
+    let accessors = (
+        board.ply(),
+        board.side_to_move(),
+        board.forced_field().map(|f| f as usize),
+        board.blocked_count() as usize,
+    );
+    if accessors != (ply, reference.turn, reference.forced, blocked) {
+        return Some(format!(
+            "(ply, side to move, forced field, blocked count): bitboard={:?}, reference={:?}",
+            accessors,
+            (ply, reference.turn, reference.forced, blocked)
+        ));
+    }
+    if board.hash() != board.compute_hash() {
+        return Some(format!(
+            "hash: incremental={:#018x}, from scratch={:#018x}",
+            board.hash(),
+            board.compute_hash()
+        ));
+    }
+    for field in 0..9 {
+        let (actual, expected) = (board.field_status(field as Index), reference.status[field]);
+        if actual != expected {
+            return Some(format!(
+                "field {} status: bitboard={:?}, reference={:?}",
+                field, actual, expected
+            ));
+        }
+    }
+    if board.result() != reference.result() {
+        return Some(format!(
+            "result: bitboard={:?}, reference={:?}",
+            board.result(),
+            reference.result()
+        ));
+    }
+    if !reference.over {
+        // the reference generates moves in the natural order, which is a contract
+        let actual: Vec<_> = generated_moves(board, MoveOrder::Natural)
+            .iter()
+            .map(cell)
+            .collect();
+        let expected = reference.legal_moves();
+        let mut mask = [0; 9];
+        for &(field, square) in &expected {
+            mask[field] |= 1 << square;
+        }
+        if board.legal_mask() != mask {
+            return Some(format!(
+                "legal mask: bitboard={:?}, reference={:?}",
+                board.legal_mask(),
+                mask
+            ));
+        }
+        if actual != expected {
+            return Some(format!(
+                "legal moves:\n  bitboard:  {}\n  reference: {}",
+                format_moves(&actual),
+                format_moves(&expected)
+            ));
+        }
+        let iterated: Vec<_> = board
+            .moves()
+            .map(|pos| (pos.field() as usize, pos.square() as usize))
+            .collect();
+        if iterated != expected || board.moves().len() != expected.len() {
+            return Some(format!(
+                "moves iterator ({} reported):\n  bitboard:  {}\n  reference: {}",
+                board.moves().len(),
+                format_moves(&iterated),
+                format_moves(&expected)
+            ));
+        }
+        let mut reordered: Vec<_> = generated_moves(board, MoveOrder::CenterFirst)
+            .iter()
+            .map(cell)
+            .collect();
+        reordered.sort_unstable();
+        if reordered != expected {
+            return Some(format!(
+                "center-first moves:\n  bitboard:  {}\n  reference: {}",
+                format_moves(&reordered),
+                format_moves(&expected)
+            ));
+        }
+    }
+    None
+}
+
+fn check_sequence(rules: Rules, moves: &[Cell]) -> Outcome {
+    let mut board = Bitboard::with_rules(rules);
+    let mut reference = Reference::with_rules(rules);
+    for (ply, &target) in moves.iter().enumerate() {
+        if let Some(reason) = compare(&mut board, &reference) {
+            return Outcome::Diverged { ply, reason };
+        }
+        if !reference.legal_moves().contains(&target) {
+            return Outcome::Illegal;
+        }
+        let mov = generated_moves(&mut board, MoveOrder::Natural)
+            .into_iter()
+            .find(|m| cell(m) == target);
+        let mov = match mov {
+            Some(mov) => mov,
+            None => unreachable!("move sets already compared equal"),
+        };
+        let before = board;
+        board.make_move(mov.pos());
+        let after = board;
+        board.undo_move(&mov);
+        if board != before {
+            let reason = format!(
+                "undo did not restore the position:\n  changes: {:?}\n  before: {:?}\n  after:  {:?}",
+                before.diff(&board),
+                before,
+                board
+            );
+            return Outcome::Diverged {
+                ply: ply + 1,
+                reason,
+            };
+        }
+        board = after;
+        reference.play(target);
+    }
+    match compare(&mut board, &reference) {
+        Some(reason) => Outcome::Diverged {
+            ply: moves.len(),
+            reason,
+        },
+        None => Outcome::Consistent,
+    }
+}
+
+// Greedily drop moves while the (still legal) sequence keeps diverging.
+fn minimize(rules: Rules, mut moves: Vec<Cell>) -> Vec<Cell> {
+    let mut i = 0;
+    while i < moves.len() {
+        let mut candidate = moves.clone();
+        candidate.remove(i);
+        match check_sequence(rules, &candidate) {
+            Outcome::Diverged { ply, .. } => {
+                candidate.truncate(ply);
+                moves = candidate;
+            }
+            _ => i += 1,
+        }
+    }
+    moves
+}
+
+fn random_game(rules: Rules, rng: &mut Rng) -> Vec<Cell> {
+    let mut reference = Reference::with_rules(rules);
+    let mut moves = Vec::new();
+    loop {
+        let legal = reference.legal_moves();
+        if legal.is_empty() {
+            return moves;
+        }
+        let target = legal[rng.below(legal.len())];
+        reference.play(target);
+        moves.push(target);
+    }
+}
+
+/// Plays `games` random games, cross-checking [`Bitboard`] against the
+/// reference rules at every ply; returns the total number of plies checked.
+/// The games take turns with the default rules, the fields-won tie-break
+/// and playable won fields.
+pub fn verify_movegen(games: usize, seed: u64) -> Result<usize, Divergence> {
+    let mut plies = 0;
+    for game in 0..games {
+        let rules = RULE_SETS[game % RULE_SETS.len()];
+        // independent stream per game so any single game can be replayed
+        let moves = random_game(rules, &mut Rng::for_worker(seed, game));
+        match check_sequence(rules, &moves) {
+            Outcome::Consistent => plies += moves.len(),
+            Outcome::Illegal => unreachable!("random games are generated by the reference"),
+            Outcome::Diverged { ply, .. } => {
+                let moves = minimize(rules, moves[..ply].to_vec());
+                let reason = match check_sequence(rules, &moves) {
+                    Outcome::Diverged { reason, .. } => reason,
+                    _ => unreachable!("minimized sequence must still diverge"),
+                };
+                let moves = moves
+                    .iter()
+                    .filter_map(|&(f, s)| Pos::new(f as Index, s as Index))
+                    .collect();
+                return Err(Divergence {
+                    game,
+                    seed,
+                    rules,
+                    moves,
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(plies)
+}
+
+type Parser = fn(&str);
+
+// every parser must reject bad input with an error rather than panic
+const PARSERS: &[(&str, Parser)] = &[
+    ("pattern", |s| {
+        let _ = Pattern::parse(s);
+    }),
+    ("move", |s| {
+        let _ = Bitboard::new().parse_move(s);
+    }),
+    ("moves", |s| {
+        let _ = parse_moves(s.split_whitespace());
+    }),
+    ("openings", |s| {
+        let _ = Openings::parse(s);
+    }),
+    ("book", |s| {
+        // past the header, so that the entries get parsed
+        let mut bytes = book::MAGIC.to_vec();
+        bytes.extend_from_slice(&book::VERSION.to_le_bytes());
+        bytes.extend_from_slice(s.as_bytes());
+        let _ = Book::read_from(&bytes[..]);
+    }),
+    ("game line", |s| {
+        let _ = parse_game_line(Bitboard::new(), s);
+    }),
+    ("protocol", |s| {
+        // one session for all inputs: a new engine would allocate its table
+        thread_local! {
+            static ENGINE: RefCell<Engine> =
+                RefCell::new(Engine::with_output(Arc::new(Mutex::new(io::sink()))));
+        }
+        ENGINE.with(|engine| {
+            let mut engine = engine.borrow_mut();
+            for command in [
+                "",
+                "position startpos moves ",
+                "setoption name TieBreak value ",
+                "go depth ",
+            ] {
+                engine.handle(&format!("{}{}", command, s));
+            }
+            engine.stop();
+        });
+    }),
+];
+
+const ALPHABET: &str = "xoXO.?/ \t\n-+:,=\"#[]0123456789aiz\u{0}\u{e9}\u{2500}\u{1f600}";
+
+fn random_input(rng: &mut Rng) -> String {
+    let len = rng.below(32);
+    (0..len)
+        .map(|_| match rng.below(8) {
+            0 => std::char::from_u32(rng.next_u64() as u32 % 0x11_0000).unwrap_or('?'),
+            _ => {
+                let n = ALPHABET.chars().count();
+                ALPHABET.chars().nth(rng.below(n)).unwrap_or('?')
+            }
+        })
+        .collect()
+}
+
+/// Feeds `cases` random, mostly malformed inputs to every parser; returns
+/// the number of parsers checked.
+pub fn verify_parsers(cases: usize, seed: u64) -> Result<usize, ParserPanic> {
+    let mut rng = Rng::new(seed);
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let mut failure = None;
+    'outer: for case in 0..cases {
+        let input = random_input(&mut rng);
+        for &(parser, parse) in PARSERS {
+            if panic::catch_unwind(|| parse(&input)).is_err() {
+                failure = Some(ParserPanic {
+                    parser,
+                    case,
+                    seed,
+                    input,
+                });
+                break 'outer;
+            }
+        }
+    }
+    panic::set_hook(hook);
+    match failure {
+        Some(failure) => Err(failure),
+        None => Ok(PARSERS.len()),
+    }
+}
@@ -0,0 +1,58 @@
+pub const NAME: &str = env!("CARGO_PKG_NAME");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
+pub const GIT_HASH: &str = env!("UTTT_GIT_HASH");
+
+/// Cargo features compiled into this build; keep in sync with Cargo.toml.
+pub fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "alloc-tracker") {
+        features.push("alloc-tracker");
+    }
+    if cfg!(feature = "check-undo") {
+        features.push("check-undo");
+    }
+    if cfg!(feature = "unchecked") {
+        features.push("unchecked");
+    }
+    features
+}
+
+pub fn simd_level() -> &'static str {
+    if cfg!(target_feature = "avx2") {
+        "avx2"
+    } else if cfg!(target_feature = "sse4.1") {
+        "sse4.1"
+    } else if cfg!(target_feature = "sse2") {
+        "sse2"
+    } else if cfg!(target_feature = "neon") {
+        "neon"
+    } else {
+        "none"
+    }
+}
+
+pub fn id() -> String {
+    format!("{} {} ({})", NAME, VERSION, GIT_HASH)
+}
+
+pub fn report() -> String {
+    let features = features();
+    let features = if features.is_empty() {
+        "none".into()
+    } else {
+        features.join(",")
+    };
+    format!(
+        "{}\nauthors: {}\nsimd: {}\nfeatures: {}\nprofile: {}",
+        id(),
+        AUTHORS,
+        simd_level(),
+        features,
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+    )
+}
@@ -0,0 +1,100 @@
+use crate::board::{Index, Player};
+#[cfg(doc)]
+use crate::rules::WonFields;
+
+/// Random keys for incremental position hashing.
+pub struct Keys {
+    /// Indexed by player, field and square.
+    pub stones: [[[u64; 9]; 9]; 2],
+    /// Indexed by the forced field; a free move contributes nothing.
+    pub forced: [u64; 9],
+    /// Toggled on every move, so it is present when O is to move.
+    pub side: u64,
+    /// Indexed by player and field, for the player who won the field. Only
+    /// hashed under [`WonFields::Playable`], where both players can complete
+    /// a line in a field and the stones do not tell who did so first; with
+    /// closed fields the status follows from the stones.
+    pub won: [[u64; 9]; 2],
+}
+
+pub static KEYS: Keys = generate(0x7574_7474_5f7a_6f62);
+
+const fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate(seed: u64) -> Keys {
+    let mut state = seed;
+    let mut stones = [[[0; 9]; 9]; 2];
+    let mut p = 0;
+    while p < 2 {
+        let mut field = 0;
+        while field < 9 {
+            let mut square = 0;
+            while square < 9 {
+                stones[p][field][square] = splitmix(&mut state);
+                square += 1;
+            }
+            field += 1;
+        }
+        p += 1;
+    }
+    let mut forced = [0; 9];
+    let mut field = 0;
+    while field < 9 {
+        forced[field] = splitmix(&mut state);
+        field += 1;
+    }
+    let side = splitmix(&mut state);
+    let mut won = [[0; 9]; 2];
+    let mut p = 0;
+    while p < 2 {
+        let mut field = 0;
+        while field < 9 {
+            won[p][field] = splitmix(&mut state);
+            field += 1;
+        }
+        p += 1;
+    }
+    Keys {
+        stones,
+        forced,
+        side,
+        won,
+    }
+}
+
+// callers guarantee field and square are in 0..9
+#[inline(always)]
+pub(crate) fn stone(p: Player, field: Index, square: Index) -> u64 {
+    unsafe {
+        *KEYS
+            .stones
+            .get_unchecked(p.index())
+            .get_unchecked(field as usize)
+            .get_unchecked(square as usize)
+    }
+}
+
+#[inline(always)]
+pub(crate) fn forced(field: Option<Index>) -> u64 {
+    match field {
+        Some(field) => unsafe { *KEYS.forced.get_unchecked(field as usize) },
+        None => 0,
+    }
+}
+
+// callers guarantee field is in 0..9
+#[inline(always)]
+pub(crate) fn won(p: Player, field: Index) -> u64 {
+    unsafe {
+        *KEYS
+            .won
+            .get_unchecked(p.index())
+            .get_unchecked(field as usize)
+    }
+}